@@ -0,0 +1,191 @@
+//! Polyglot `.bin` opening books, for varying the openings of self-play games and matches.
+//!
+//! A book is a sorted list of 16-byte big-endian entries: the position's Polyglot key, a
+//! move, a weight and a learn field that is read but not used. See `Game::polyglot_key`.
+
+use std::fs;
+use std::path::Path;
+use rand::Rng;
+use crate::error::Error;
+use crate::game::{Game, Move};
+
+const ENTRY_SIZE: usize = 16;
+
+/// One book entry, as stored in the file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BookEntry {
+    pub key: u64,
+    /// Polyglot's move encoding; `Game::polyglot_move` turns it into a `Move`.
+    pub raw_move: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+/// How `OpeningBook::choose` picks among the moves for a position.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BookSelection {
+    /// At random, in proportion to the weights, so games branch out.
+    #[default]
+    WeightedRandom,
+    /// Always the highest weighted move.
+    Best,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct OpeningBook {
+    entries: Vec<BookEntry>,
+    selection: BookSelection,
+}
+
+impl OpeningBook {
+    /// Reads a book file.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// Reads a book from its raw contents. Entries need not be sorted.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(Error::BadBookSize(bytes.len()));
+        }
+        let mut entries: Vec<BookEntry> = bytes.chunks_exact(ENTRY_SIZE).map(|entry| BookEntry {
+            key: u64::from_be_bytes(entry[0..8].try_into().expect("8 bytes")),
+            raw_move: u16::from_be_bytes([entry[8], entry[9]]),
+            weight: u16::from_be_bytes([entry[10], entry[11]]),
+            learn: u32::from_be_bytes(entry[12..16].try_into().expect("4 bytes")),
+        }).collect();
+        entries.sort_by_key(|entry| entry.key);
+        Ok(OpeningBook { entries, selection: BookSelection::default() })
+    }
+
+    pub fn with_selection(mut self, selection: BookSelection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    pub fn selection(&self) -> BookSelection {
+        self.selection
+    }
+
+    /// Number of entries, over all positions.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The book's entries for `game`'s position, in file order.
+    pub fn entries(&self, game: &Game) -> &[BookEntry] {
+        let key = game.polyglot_key();
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let end = self.entries.partition_point(|entry| entry.key <= key);
+        &self.entries[start..end]
+    }
+
+    /// The legal book moves for `game` with their weights, highest weight first. Moves with
+    /// weight 0 are kept here but never chosen.
+    pub fn moves(&self, game: &Game) -> Vec<(Move, u16)> {
+        let mut moves: Vec<(Move, u16)> = self.entries(game).iter()
+            .filter_map(|entry| Some((game.polyglot_move(entry.raw_move)?, entry.weight)))
+            .collect();
+        moves.sort_by_key(|&(_, weight)| std::cmp::Reverse(weight));
+        moves
+    }
+
+    /// The highest weighted book move, or `None` once the game has left the book.
+    pub fn best_move(&self, game: &Game) -> Option<Move> {
+        self.moves(game).into_iter().find(|&(_, weight)| weight > 0).map(|(mv, _)| mv)
+    }
+
+    /// A book move picked at random in proportion to its weight.
+    pub fn weighted_move<R: Rng + ?Sized>(&self, game: &Game, rng: &mut R) -> Option<Move> {
+        let moves = self.moves(game);
+        let total: u32 = moves.iter().map(|&(_, weight)| u32::from(weight)).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut pick = rng.gen_range(0..total);
+        for (mv, weight) in moves {
+            if pick < u32::from(weight) {
+                return Some(mv);
+            }
+            pick -= u32::from(weight);
+        }
+        unreachable!("the pick is below the total weight")
+    }
+
+    /// A book move for `game` chosen by the book's `BookSelection`.
+    pub fn choose(&self, game: &Game) -> Option<Move> {
+        match self.selection {
+            BookSelection::WeightedRandom => self.weighted_move(game, &mut rand::thread_rng()),
+            BookSelection::Best => self.best_move(game),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// The raw bytes of a book entry for the UCI move `uci` in `game`.
+    pub(crate) fn entry(game: &Game, uci: &str, weight: u16) -> Vec<u8> {
+        let mv: Move = uci.parse().unwrap();
+        let square = |sq: chess::Square| (sq.get_rank().to_index() << 3 | sq.get_file().to_index()) as u16;
+        let raw = square(mv.source()) << 6 | square(mv.to());
+        let mut bytes = game.polyglot_key().to_be_bytes().to_vec();
+        bytes.extend(raw.to_be_bytes());
+        bytes.extend(weight.to_be_bytes());
+        bytes.extend(0u32.to_be_bytes());
+        bytes
+    }
+
+    fn start_book() -> OpeningBook {
+        let game = Game::new();
+        let mut after_e4 = Game::new();
+        after_e4.make_move("e2e4").unwrap();
+        let bytes = [
+            entry(&after_e4, "c7c5", 10),
+            entry(&game, "d2d4", 1),
+            entry(&game, "e2e4", 3),
+            entry(&game, "e2e5", 50),
+            entry(&game, "a2a3", 0),
+        ].concat();
+        OpeningBook::from_bytes(&bytes).unwrap()
+    }
+
+    #[test]
+    fn test_book_lookup() {
+        let book = start_book();
+        assert_eq!(book.len(), 5);
+        let game = Game::new();
+        assert_eq!(book.entries(&game).len(), 4);
+        let moves: Vec<String> = book.moves(&game).iter().map(|(mv, _)| mv.to_string()).collect();
+        assert_eq!(moves, ["e2e4", "d2d4", "a2a3"], "Illegal entries are skipped.");
+        assert_eq!(book.best_move(&game), Some("e2e4".parse().unwrap()));
+
+        let mut game = Game::new();
+        game.make_move("e2e4").unwrap();
+        assert_eq!(book.with_selection(BookSelection::Best).choose(&game), Some("c7c5".parse().unwrap()));
+        game.make_move("c7c5").unwrap();
+        assert_eq!(start_book().choose(&game), None, "Out of book.");
+
+        assert!(matches!(OpeningBook::from_bytes(&[0; 17]), Err(Error::BadBookSize(17))));
+        assert!(matches!(OpeningBook::open("no_such_book.bin"), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn test_weighted_selection() {
+        let book = start_book();
+        let game = Game::new();
+        let mut rng = StdRng::seed_from_u64(7);
+        let e4 = "e2e4".parse().unwrap();
+        let picks: Vec<Move> = (0..400).map(|_| book.weighted_move(&game, &mut rng).unwrap()).collect();
+        let e4_count = picks.iter().filter(|&&mv| mv == e4).count();
+        assert!((250..350).contains(&e4_count), "e4 has 3/4 of the weight, got {}", e4_count);
+        assert!(!picks.contains(&"a2a3".parse().unwrap()), "Zero weight is never picked.");
+    }
+}
@@ -1,6 +1,9 @@
 
 use tch::{nn, nn::Module, Device, Tensor};
+use std::fs::File;
+use std::io;
 use std::sync::{Arc, Mutex};
+use crate::error::Error;
 
 
 pub struct ChessAIModel {
@@ -10,9 +13,14 @@ pub struct ChessAIModel {
 
 impl ChessAIModel {
     pub fn new() -> Self {
+        Self::with_input_size(384)
+    }
+
+    /// A fresh network for encodings of another length, e.g. `Variant::input_size`.
+    pub fn with_input_size(input_size: i64) -> Self {
         let vs = nn::VarStore::new(Device::Cpu);
         let net = nn::seq()
-            .add(nn::linear(vs.root(), 384, 128, Default::default()))
+            .add(nn::linear(vs.root(), input_size, 128, Default::default()))
             .add_fn(|xs| xs.relu())
             .add(nn::linear(vs.root(), 128, 64, Default::default()))
             .add_fn(|xs| xs.relu())
@@ -28,23 +36,18 @@ impl ChessAIModel {
         let output = net.forward(input);
         output.double_value(&[0])
     }
-    pub fn from_file(filepath: &str) -> Self {
-        let mut vs = nn::VarStore::new(Device::Cpu);
-        vs.load(filepath).expect("Failed to load model from file");
-        let net = nn::seq()
-            .add(nn::linear(vs.root(), 384, 128, Default::default()))
-            .add_fn(|xs| xs.relu())
-            .add(nn::linear(vs.root(), 128, 64, Default::default()))
-            .add_fn(|xs| xs.relu())
-            .add(nn::linear(vs.root(), 64, 1, Default::default()));
-        ChessAIModel {
-            vs,
-            net: Arc::new(Mutex::new(Box::new(net))),
-        }
+    /// Loads weights saved by `save_to_file` from a network with `input_size` inputs. The
+    /// network is built first so the file's tensors are matched against it: a file that
+    /// cannot be opened is an I/O error, one whose tensors do not fit is `IncompatibleWeights`.
+    pub fn from_file(filepath: &str, input_size: i64) -> Result<Self, Error> {
+        File::open(filepath)?;
+        let mut model = Self::with_input_size(input_size);
+        model.vs.load(filepath).map_err(|e| Error::IncompatibleWeights(e.to_string()))?;
+        Ok(model)
     }
 
-    pub fn save_to_file(&self, filepath: &str) {
-        self.vs.save(filepath).expect("Failed to save model to file");
+    pub fn save_to_file(&self, filepath: &str) -> Result<(), Error> {
+        self.vs.save(filepath).map_err(|e| Error::Io(io::Error::other(e.to_string())))
     }
 }
 
@@ -0,0 +1,276 @@
+//! Extended Position Description (EPD) test suites such as WAC and STS.
+//!
+//! Each line holds the first four FEN fields followed by `opcode operand...;` operations.
+//! The opcodes a suite needs are read: `bm` (best moves), `am` (moves to avoid), `id` and
+//! `c0` (comment), plus `hmvc` and `fmvn` for the move counters. Others are kept as text.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use mcts::transposition_table::ApproxTable;
+use mcts::tree_policy::UCTPolicy;
+use mcts::MCTSManager;
+use crate::error::Error;
+use crate::game::{Game, Move};
+use crate::mcts::{ChessEvaluator, ChessMCTS, ChessMCTSState, ChessModel};
+
+/// One test position and what a search is expected to play there.
+#[derive(Clone, Debug)]
+pub struct EpdPosition {
+    pub game: Game,
+    /// Moves that solve the position (`bm`); any one of them will do.
+    pub best_moves: Vec<Move>,
+    /// Moves that fail the position (`am`).
+    pub avoid_moves: Vec<Move>,
+    pub id: Option<String>,
+    pub comment: Option<String>,
+    /// Every operation as read, opcode and operands, including the ones above.
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+impl EpdPosition {
+    /// Parses a single EPD line. `bm` and `am` moves are SAN, checked against the position.
+    pub fn parse(line: &str) -> Result<Self, Error> {
+        let line = line.trim();
+        let mut fen_fields = Vec::with_capacity(4);
+        let mut rest = line;
+        for _ in 0..4 {
+            rest = rest.trim_start();
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            if end == 0 {
+                return Err(Error::InvalidEpd(format!("four FEN fields are needed: {}", line)));
+            }
+            fen_fields.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+        let operations = parse_operations(rest)?;
+
+        let operand = |opcode: &str| {
+            operations.iter().find(|(op, _)| op == opcode).and_then(|(_, operands)| operands.first())
+        };
+        let halfmove_clock = operand("hmvc").map_or("0", String::as_str);
+        let fullmove_number = operand("fmvn").map_or("1", String::as_str);
+        let fen = format!("{} {} {}", fen_fields.join(" "), halfmove_clock, fullmove_number);
+        let game = Game::from_fen(&fen)?;
+
+        let moves = |opcode: &str| -> Result<Vec<Move>, Error> {
+            operations.iter()
+                .filter(|(op, _)| op == opcode)
+                .flat_map(|(_, operands)| operands)
+                .map(|san| game.parse_san(san).map_err(|e| Error::Within(format!("{} {}", opcode, san), Box::new(e))))
+                .collect()
+        };
+        let best_moves = moves("bm")?;
+        let avoid_moves = moves("am")?;
+
+        Ok(EpdPosition {
+            id: operand("id").cloned(),
+            comment: operand("c0").cloned(),
+            game,
+            best_moves,
+            avoid_moves,
+            operations,
+        })
+    }
+
+    /// Whether playing `mv` solves the position: it is one of the best moves, if any are
+    /// given, and none of the moves to avoid.
+    pub fn is_solved_by(&self, mv: Move) -> bool {
+        (self.best_moves.is_empty() || self.best_moves.contains(&mv)) && !self.avoid_moves.contains(&mv)
+    }
+}
+
+/// Parses every position in an EPD file. Blank lines and lines starting with `#` are skipped.
+pub fn parse_epd(text: &str) -> Result<Vec<EpdPosition>, Error> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| EpdPosition::parse(line).map_err(|e| Error::Within(format!("Line {}", i + 1), Box::new(e))))
+        .collect()
+}
+
+/// Splits `opcode operand...;` operations. Quoted operands may contain spaces and `;`.
+fn parse_operations(text: &str) -> Result<Vec<(String, Vec<String>)>, Error> {
+    let mut operations = Vec::new();
+    let mut words = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ';' => {
+                if !words.is_empty() {
+                    let opcode = words.remove(0);
+                    operations.push((opcode, std::mem::take(&mut words)));
+                }
+            }
+            '"' => {
+                let mut word = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(Error::InvalidEpd(format!("unterminated string in operations: {}", text.trim()))),
+                    }
+                }
+                words.push(word);
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut word = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || next == ';' || next == '"' {
+                        break;
+                    }
+                    word.push(next);
+                    chars.next();
+                }
+                words.push(word);
+            }
+        }
+    }
+    if !words.is_empty() {
+        return Err(Error::InvalidEpd(format!("operation missing its ';': {}", text.trim())));
+    }
+
+    Ok(operations)
+}
+
+/// How long to search each position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchBudget {
+    /// A fixed number of MCTS playouts, for results that do not depend on the machine.
+    Nodes(u64),
+    Time(Duration),
+}
+
+/// The outcome of searching one position.
+#[derive(Clone, Debug)]
+pub struct EpdResult {
+    /// The position's `id`, or its number in the suite when it has none.
+    pub id: String,
+    /// The move the search chose, in SAN, or `None` if the position has no legal move.
+    pub played: Option<String>,
+    pub solved: bool,
+}
+
+/// Per-position results of a suite run, printed one line each followed by the totals.
+#[derive(Clone, Debug, Default)]
+pub struct EpdReport {
+    pub results: Vec<EpdResult>,
+}
+
+impl EpdReport {
+    pub fn solved(&self) -> usize {
+        self.results.iter().filter(|result| result.solved).count()
+    }
+
+    pub fn total(&self) -> usize {
+        self.results.len()
+    }
+}
+
+impl fmt::Display for EpdReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for result in &self.results {
+            let verdict = if result.solved { "solved" } else { "failed" };
+            writeln!(f, "{} {} {}", result.id, verdict, result.played.as_deref().unwrap_or("(none)"))?;
+        }
+        let percent = if self.total() == 0 { 0.0 } else { 100.0 * self.solved() as f64 / self.total() as f64 };
+        write!(f, "Solved {}/{} ({:.1}%)", self.solved(), self.total(), percent)
+    }
+}
+
+/// Searches every position with `ChessMCTS` for `budget` and checks the move it picks.
+pub fn run_epd_suite(positions: &[EpdPosition], budget: SearchBudget, model: Arc<dyn ChessModel>) -> EpdReport {
+    let results = positions.iter().enumerate().map(|(i, position)| {
+        let state = ChessMCTSState::new(position.game.clone());
+        let evaluator = ChessEvaluator::new(Box::new(model.clone()));
+        let mut mcts = MCTSManager::new(state, ChessMCTS, evaluator, UCTPolicy::new(0.5), ApproxTable::new(1024));
+        match budget {
+            SearchBudget::Nodes(nodes) => mcts.playout_n(nodes),
+            SearchBudget::Time(duration) => {
+                let start = Instant::now();
+                mcts.playout_until(|| start.elapsed() >= duration);
+            }
+        }
+
+        let best = mcts.best_move();
+        EpdResult {
+            id: position.id.clone().unwrap_or_else(|| format!("#{}", i + 1)),
+            played: best.map(|mv| position.game.to_san(mv).expect("the search only plays legal moves")),
+            solved: best.is_some_and(|mv| position.is_solved_by(mv)),
+        }
+    }).collect();
+
+    EpdReport { results }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcts::ModelOutput;
+
+    struct UniformModel;
+
+    impl ChessModel for UniformModel {
+        fn evaluate(&self, game: &Game) -> ModelOutput {
+            let moves = game.legal_moves().len();
+            ModelOutput { value: 0.0, policy: vec![1.0 / moves as f64; moves] }
+        }
+    }
+
+    #[test]
+    fn test_parse_epd_position() {
+        let position = EpdPosition::parse(
+            r#"2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id "WAC.001"; c0 "mate; in 3";"#,
+        ).unwrap();
+        assert_eq!(position.best_moves, ["g3g6".parse::<Move>().unwrap()]);
+        assert!(position.avoid_moves.is_empty());
+        assert_eq!(position.id.as_deref(), Some("WAC.001"));
+        assert_eq!(position.comment.as_deref(), Some("mate; in 3"));
+        assert_eq!(position.game.to_fen(), "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1");
+
+        let position = EpdPosition::parse(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - bm e4 d4; am f3; hmvc 3; fmvn 12; ce 15;",
+        ).unwrap();
+        assert_eq!(position.best_moves.len(), 2);
+        assert_eq!(position.avoid_moves, ["f2f3".parse::<Move>().unwrap()]);
+        assert!(position.operations.contains(&("ce".to_string(), vec!["15".to_string()])));
+        assert_eq!(position.game.halfmove_clock(), 3);
+        assert_eq!(position.game.fullmove_number(), 12);
+        assert!(position.is_solved_by("e2e4".parse().unwrap()));
+        assert!(!position.is_solved_by("g1f3".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_parse_epd_errors() {
+        assert!(EpdPosition::parse("8/8/8 w").is_err());
+        assert!(EpdPosition::parse("4k3/8/8/8/8/8/8/4K3 w - - bm Qh5;").is_err(), "bm must be legal");
+        assert!(EpdPosition::parse(r#"4k3/8/8/8/8/8/8/4K3 w - - id "open;"#).is_err());
+        assert!(EpdPosition::parse("4k3/8/8/8/8/8/8/4K3 w - - bm Kd2").is_err(), "missing ';'");
+
+        let text = "# a comment\n\n4k3/8/8/8/8/8/8/4K3 w - - bm Kd2;\n4k3/8/8/8/8/8/8/4K3 w - - bm Kd9;\n";
+        let error = parse_epd(text).unwrap_err();
+        assert!(matches!(&error, Error::Within(line, e) if line == "Line 4" && matches!(**e, Error::Within(..))), "{}", error);
+        assert_eq!(error.to_string(), "Line 4: bm Kd9: Malformed move: bad destination square in 'Kd9'");
+    }
+
+    #[test]
+    fn test_run_epd_suite() {
+        // White's only legal move is Kxg2, so the search cannot miss it.
+        let text = "7k/8/8/8/8/8/6q1/7K w - - bm Kxg2; id \"forced\";\n\
+                    7k/8/8/8/8/8/6q1/7K w - - am Kxg2;\n";
+        let positions = parse_epd(text).unwrap();
+        let report = run_epd_suite(&positions, SearchBudget::Nodes(50), Arc::new(UniformModel));
+
+        assert_eq!(report.total(), 2);
+        assert_eq!(report.solved(), 1);
+        assert!(report.results[0].solved);
+        assert_eq!(report.results[1].id, "#2");
+        assert_eq!(report.results[1].played.as_deref(), Some("Kxg2"));
+        assert_eq!(report.to_string(), "forced solved Kxg2\n#2 failed Kxg2\nSolved 1/2 (50.0%)");
+
+        let timed = run_epd_suite(&positions[..1], SearchBudget::Time(Duration::from_millis(20)), Arc::new(UniformModel));
+        assert_eq!(timed.solved(), 1);
+    }
+}
@@ -0,0 +1,82 @@
+//! The error type for reading moves, positions and game records and for loading and saving
+//! models.
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use crate::game::SetupIssue;
+
+#[derive(Debug)]
+pub enum Error {
+    /// A well-formed move that cannot be played in the position, e.g. `e2e5`.
+    IllegalMove(String),
+    /// Text that is not a move at all, with what is wrong with it.
+    MalformedMove(String),
+    /// A promotion to something other than a queen, rook, bishop, knight or (Antichess) king.
+    BadPromotion(String),
+    /// A FEN that cannot be read, with the offending field.
+    InvalidFen(String),
+    /// A position from a `PositionBuilder` that cannot be played, with everything wrong with it.
+    InvalidPosition(Vec<SetupIssue>),
+    Io(io::Error),
+    /// A model file that does not fit the network, e.g. a checkpoint of another size.
+    IncompatibleWeights(String),
+    /// A Chess960 starting position number outside 0–959.
+    Chess960OutOfRange(u16),
+    /// A game that cannot switch Chess960 castling on or off, with the reason.
+    Chess960Unavailable(String),
+    /// `Game::claim_draw` with no repetition or fifty-move draw to claim.
+    NoDrawToClaim,
+    /// PGN text that cannot be read, with what is wrong with it.
+    InvalidPgn(String),
+    /// An EPD line that cannot be read, with what is wrong with it.
+    InvalidEpd(String),
+    /// A Polyglot book whose length in bytes, given, is not a whole number of 16-byte entries.
+    BadBookSize(usize),
+    /// A directory with no Syzygy table files in it.
+    NoTables(PathBuf),
+    /// An error in one part of a larger input, e.g. `Game 3, move 12` of a PGN file.
+    Within(String, Box<Error>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::IllegalMove(mv) => write!(f, "Illegal move: {}", mv),
+            Error::MalformedMove(reason) => write!(f, "Malformed move: {}", reason),
+            Error::BadPromotion(piece) => write!(f, "Invalid promotion piece: {}", piece),
+            Error::InvalidFen(reason) => write!(f, "Invalid FEN: {}", reason),
+            Error::InvalidPosition(issues) => {
+                let issues: Vec<String> = issues.iter().map(|issue| issue.to_string()).collect();
+                write!(f, "Invalid position: {}", issues.join("; "))
+            }
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::IncompatibleWeights(reason) => write!(f, "Incompatible model weights: {}", reason),
+            Error::Chess960OutOfRange(index) => write!(f, "Chess960 position {} is out of range 0-959", index),
+            Error::Chess960Unavailable(reason) => write!(f, "Cannot switch Chess960 castling: {}", reason),
+            Error::NoDrawToClaim => write!(f, "No draw can be claimed in this position"),
+            Error::InvalidPgn(reason) => write!(f, "Invalid PGN: {}", reason),
+            Error::InvalidEpd(reason) => write!(f, "Invalid EPD: {}", reason),
+            Error::BadBookSize(size) => write!(f, "Polyglot book size {} is not a multiple of 16 bytes", size),
+            Error::NoTables(dir) => write!(f, "No Syzygy tables in {}", dir.display()),
+            Error::Within(location, e) => write!(f, "{}: {}", location, e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Within(_, e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
@@ -1,51 +1,517 @@
 use std::fmt;
 use std::str::FromStr;
 use std::collections::HashMap;
-use chess::{Board, MoveGen, ChessMove, BoardStatus, Square, Piece, Color};
+use chess::{BitBoard, Board, MoveGen, ChessMove, BoardStatus, Square, Piece, Color};
 use mcts::MCTSManager;
 use crate::mcts::ChessMCTS;
+use crate::error::Error;
+use self::chess960::CastlingRooks;
+use self::crazyhouse::Pockets;
+use self::antichess::AntichessBoard;
+
+mod san;
+mod perft;
+mod chess960;
+mod variant;
+mod crazyhouse;
+mod antichess;
+mod diagram;
+mod svg;
+mod see;
+mod features;
+mod polyglot;
+mod syzygy;
+mod setup;
+mod encoding;
+#[cfg(feature = "serde")]
+mod serialization;
+
+pub use perft::PerftRules;
+pub use variant::Variant;
+pub use diagram::DiagramOptions;
+pub use svg::{Arrow, SvgOptions};
+pub use features::{SideFeatures, FEATURE_PLANES};
+pub use syzygy::{Tablebase, Wdl};
+pub use setup::{PositionBuilder, SetupIssue};
+pub use encoding::{Encoding, Perspective, ALPHA_ZERO_PLANES};
 
 #[derive(Clone, Debug)]
 pub struct Game {
     board: Board,
     positions: HashMap<u64, u32>,
+    history: Vec<MoveRecord>,
+    /// Plies since the last capture or pawn move, as in FEN.
+    halfmove_clock: u32,
+    /// Starts at 1 and increases after each Black move, as in FEN.
+    fullmove_number: u32,
+    /// Outcome decided off the board: resignation, timeout, agreement, adjudication or a claimed draw.
+    declared: Option<GameOutcome>,
+    draw_rules: DrawRules,
+    /// Castling rights when castling follows Chess960 rules; the board then holds none.
+    chess960: Option<CastlingRooks>,
+    variant: Variant,
+    /// Checks given by White and Black, which decide Three-check games.
+    checks: [u32; 2],
+    /// Crazyhouse pieces in hand.
+    pockets: Pockets,
+    /// The position in Antichess, which `board` cannot hold; `board` is then left unused.
+    antichess: Option<AntichessBoard>,
 }
 
+/// How threefold repetition and the fifty-move rule end a game.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DrawRules {
+    /// Threefold repetition and the fifty-move rule end the game at once, which keeps
+    /// self-play games short.
+    #[default]
+    Immediate,
+    /// FIDE rules: threefold repetition and fifty-move draws have to be claimed with
+    /// `Game::claim_draw`, while fivefold repetition and the seventy-five-move rule
+    /// still end the game automatically.
+    Claimable,
+}
+
+/// A move as used by search: a `Copy` value that never allocates. `FromStr` and `Display`
+/// convert to and from UCI coordinate notation (`e2e4`, `e7e8q`, drops as `N@f3`) at the edges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Move {
+    mv: ChessMove,
+    /// The piece a Crazyhouse drop puts on the board; `mv` then starts and ends on its square.
+    drop: Option<Piece>,
+}
+
+impl Move {
+    pub fn new(from: Square, to: Square, promotion: Option<Piece>) -> Self {
+        ChessMove::new(from, to, promotion).into()
+    }
+
+    /// Dropping a piece from the pocket onto `to`, as in Crazyhouse.
+    pub fn new_drop(piece: Piece, to: Square) -> Self {
+        Move { mv: ChessMove::new(to, to, None), drop: Some(piece) }
+    }
+
+    /// The square the piece moves from; for a drop, the square it is dropped on.
+    pub fn source(&self) -> Square {
+        self.mv.get_source()
+    }
+
+    pub fn to(&self) -> Square {
+        self.mv.get_dest()
+    }
+
+    pub fn promotion(&self) -> Option<Piece> {
+        self.mv.get_promotion()
+    }
+
+    /// The piece dropped, if this is a drop.
+    pub fn dropped_piece(&self) -> Option<Piece> {
+        self.drop
+    }
+}
+
+impl From<ChessMove> for Move {
+    fn from(mv: ChessMove) -> Self {
+        Move { mv, drop: None }
+    }
+}
+
+/// Drops have no `ChessMove` equivalent and convert to a move from their square to itself.
+impl From<Move> for ChessMove {
+    fn from(mv: Move) -> Self {
+        mv.mv
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.drop {
+            Some(piece) => write!(f, "{}@{}", piece.to_string(Color::White), self.to()),
+            None => write!(f, "{}", self.mv),
+        }
+    }
+}
+
+impl FromStr for Move {
+    type Err = Error;
+
+    /// Parses UCI coordinate notation. This only checks the syntax, not legality.
+    fn from_str(move_str: &str) -> Result<Self, Error> {
+        if move_str.len() < 4 {
+            return Err(Error::MalformedMove(format!("'{}' is too short", move_str)));
+        }
+
+        if move_str.get(1..2) == Some("@") {
+            let piece = match &move_str[..1] {
+                "P" | "p" => Piece::Pawn,
+                "N" | "n" => Piece::Knight,
+                "B" | "b" => Piece::Bishop,
+                "R" | "r" => Piece::Rook,
+                "Q" | "q" => Piece::Queen,
+                _ => return Err(Error::MalformedMove(format!("bad drop piece in '{}'", move_str))),
+            };
+            let to_square = Square::from_str(&move_str[2..])
+                .map_err(|_| Error::MalformedMove(format!("bad to-square in '{}'", move_str)))?;
+            return Ok(Move::new_drop(piece, to_square));
+        }
+
+        let bad_square = |side: &str| Error::MalformedMove(format!("bad {}-square in '{}'", side, move_str));
+        let from_str = move_str.get(0..2).ok_or_else(|| bad_square("from"))?;
+        let to_str = move_str.get(2..4).ok_or_else(|| bad_square("to"))?;
+
+        let from_square = Square::from_str(from_str)
+            .map_err(|_| bad_square("from"))?;
+        let to_square = Square::from_str(to_str)
+            .map_err(|_| bad_square("to"))?;
+
+        let promotion_piece = if move_str.len() > 4 {
+            // The fifth character in the move_str might represent the promotion piece.
+            // For example, 'q' for queen, 'r' for rook, 'b' for bishop, 'n' for knight.
+            match &move_str[4..] {
+                "q" => Some(Piece::Queen),
+                "r" => Some(Piece::Rook),
+                "b" => Some(Piece::Bishop),
+                "n" => Some(Piece::Knight),
+                // Only Antichess allows it, but it is valid syntax everywhere.
+                "k" => Some(Piece::King),
+                other => return Err(Error::BadPromotion(other.to_string())),
+            }
+        } else {
+            None
+        };
+
+        Ok(Move::new(from_square, to_square, promotion_piece))
+    }
+}
+
+/// The side to move, or the side a result or evaluation is for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Player {
+    White,
+    Black,
+}
+
+impl Player {
+    pub fn opponent(self) -> Player {
+        match self {
+            Player::White => Player::Black,
+            Player::Black => Player::White,
+        }
+    }
+}
+
+impl From<Color> for Player {
+    fn from(color: Color) -> Self {
+        match color {
+            Color::White => Player::White,
+            Color::Black => Player::Black,
+        }
+    }
+}
+
+impl From<Player> for Color {
+    fn from(player: Player) -> Self {
+        match player {
+            Player::White => Color::White,
+            Player::Black => Color::Black,
+        }
+    }
+}
 
+impl fmt::Display for Player {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Player::White => write!(f, "White"),
+            Player::Black => write!(f, "Black"),
+        }
+    }
+}
 
-#[derive(Debug, PartialEq)]
+/// One ply of the move stack: the move played plus everything needed to take it back.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MoveRecord {
+    pub mv: Move,
+    /// Position before `mv` was played. Antichess positions are kept in `antichess_before`.
+    pub(crate) board_before: Board,
+    /// Piece removed from the board by `mv`, including en passant captures.
+    pub captured: Option<Piece>,
+    /// How many times the position after `mv` had occurred, counting this occurrence.
+    pub repetitions: u32,
+    /// FEN halfmove clock before `mv`.
+    pub halfmove_clock: u32,
+    /// FEN fullmove number before `mv`.
+    pub fullmove_number: u32,
+    /// Chess960 castling rights before `mv`.
+    pub(crate) chess960_before: Option<CastlingRooks>,
+    /// Checks given by each side before `mv`.
+    pub(crate) checks_before: [u32; 2],
+    /// Crazyhouse pockets before `mv`.
+    pub(crate) pockets_before: Pockets,
+    /// Antichess position before `mv`.
+    pub(crate) antichess_before: Option<AntichessBoard>,
+}
+
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GameResult {
     WhiteWin,
     BlackWin,
     Draw
 }
 
+/// Why a game ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    ThreefoldRepetition,
+    FivefoldRepetition,
+    FiftyMoveRule,
+    SeventyFiveMoveRule,
+    InsufficientMaterial,
+    Resignation,
+    Timeout,
+    Agreement,
+    Adjudication,
+    /// King of the Hill: a king reached the centre.
+    KingInTheCentre,
+    /// Three-check: the third check was given.
+    ThreeChecks,
+    /// Racing Kings: a king reached the eighth rank.
+    KingOnEighthRank,
+    /// Antichess: a player lost all their pieces, and with them the game is won.
+    LostAllPieces,
+}
+
+impl Termination {
+    /// The value for a PGN `Termination` tag. The standard only distinguishes how a game
+    /// was stopped, so every over-the-board ending is `normal`.
+    pub fn pgn_tag(&self) -> &'static str {
+        match self {
+            Termination::Timeout => "time forfeit",
+            Termination::Adjudication => "adjudication",
+            _ => "normal",
+        }
+    }
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self {
+            Termination::Checkmate => "checkmate",
+            Termination::Stalemate => "stalemate",
+            Termination::ThreefoldRepetition => "threefold repetition",
+            Termination::FivefoldRepetition => "fivefold repetition",
+            Termination::FiftyMoveRule => "fifty-move rule",
+            Termination::SeventyFiveMoveRule => "seventy-five-move rule",
+            Termination::InsufficientMaterial => "insufficient material",
+            Termination::Resignation => "resignation",
+            Termination::Timeout => "timeout",
+            Termination::Agreement => "agreement",
+            Termination::Adjudication => "adjudication",
+            Termination::KingInTheCentre => "king in the centre",
+            Termination::ThreeChecks => "three checks",
+            Termination::KingOnEighthRank => "king on the eighth rank",
+            Termination::LostAllPieces => "losing all pieces",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+/// A finished game's result together with the reason it ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameOutcome {
+    pub result: GameResult,
+    pub termination: Termination,
+}
+
+impl GameOutcome {
+    pub fn new(result: GameResult, termination: Termination) -> Self {
+        GameOutcome { result, termination }
+    }
+}
+
+impl fmt::Display for GameOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let result = match self.result {
+            GameResult::WhiteWin => "White wins",
+            GameResult::BlackWin => "Black wins",
+            GameResult::Draw => "Draw",
+        };
+        write!(f, "{} by {}", result, self.termination)
+    }
+}
+
 impl Game {
     pub fn new() -> Self {
         // Default board is the standard chess starting position
         let mut g = Game {
             board: Board::default(),
             positions: HashMap::new(),
+            history: Vec::new(),
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            declared: None,
+            draw_rules: DrawRules::default(),
+            chess960: None,
+            variant: Variant::Standard,
+            checks: [0, 0],
+            pockets: Pockets::default(),
+            antichess: None,
         };
         g.increment_position_count();
         g
     }
 
     pub(crate) fn get_hash(&self) -> u64 {
-        self.board.get_hash()
+        let board_key = match &self.antichess {
+            Some(position) => position.key(),
+            None => self.board.get_hash(),
+        };
+        board_key ^ self.castling_key() ^ self.variant_key() ^ self.pockets_key()
     }
 
 
-    pub fn make_move(&mut self, move_str: &str) -> Result<Self, String> {
+    pub fn make_move(&mut self, move_str: &str) -> Result<Self, Error> {
         let parsed_move = self.parse_move(move_str)?;
-        let new_board = self.board.make_move_new(parsed_move);
+        self.apply_move(parsed_move);
 
-        self.board = new_board;
+        Ok(self.clone())
+    }
+
+    /// Plays a typed move, e.g. one taken from `legal_moves`, without cloning the game.
+    pub fn play_move(&mut self, mv: Move) -> Result<(), Error> {
+        self.check_promotion(mv)?;
+        if !self.is_legal(mv) {
+            return Err(Error::IllegalMove(mv.to_string()));
+        }
+        self.apply_move(mv);
+        Ok(())
+    }
+
+    /// Plays an already validated move and pushes it onto the move stack.
+    fn apply_move(&mut self, mv: Move) {
+        let mover: Color = self.current_player().into();
+        let board_before = self.board;
+        let chess960_before = self.chess960;
+        let checks_before = self.checks;
+        let pockets_before = self.pockets;
+        let antichess_before = self.antichess;
+        let captured = match &self.antichess {
+            Some(position) => position.captured_piece(mv.mv),
+            None => captured_piece(&board_before, mv.mv),
+        };
+        let halfmove_clock = self.halfmove_clock;
+        let fullmove_number = self.fullmove_number;
+
+        let moved = mv.drop.or(self.piece_on(mv.source()));
+        if captured.is_some() || moved == Some(Piece::Pawn) {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if self.current_player() == Player::Black {
+            self.fullmove_number += 1;
+        }
+
+        let chess960 = self.chess960_after(mv.mv);
+        let pockets = self.pockets_after(mv, captured);
+        match &mut self.antichess {
+            Some(position) => *position = position.after(mv.mv),
+            None => self.board = self.board_after(mv),
+        }
+        self.chess960 = chess960;
+        self.pockets = pockets;
+        if self.checkers() != chess::EMPTY {
+            self.checks[mover.to_index()] += 1;
+        }
         self.increment_position_count();
 
-        Ok(Self {
+        let repetitions = self.positions[&self.get_hash()];
+        self.history.push(MoveRecord {
+            mv,
+            board_before,
+            captured,
+            repetitions,
+            halfmove_clock,
+            fullmove_number,
+            chess960_before,
+            checks_before,
+            pockets_before,
+            antichess_before,
+        });
+    }
+
+    /// Takes back the last move, restoring the previous position and repetition counts.
+    /// Any declared outcome (resignation, timeout, ...) is withdrawn as well.
+    /// Returns the record of the undone move, or `None` at the start of the game.
+    pub fn undo_move(&mut self) -> Option<MoveRecord> {
+        let record = self.history.pop()?;
+        self.declared = None;
+
+        let key = self.get_hash();
+        if let Some(count) = self.positions.get_mut(&key) {
+            *count -= 1;
+            if *count == 0 {
+                self.positions.remove(&key);
+            }
+        }
+        self.board = record.board_before;
+        self.chess960 = record.chess960_before;
+        self.checks = record.checks_before;
+        self.pockets = record.pockets_before;
+        self.antichess = record.antichess_before;
+        self.halfmove_clock = record.halfmove_clock;
+        self.fullmove_number = record.fullmove_number;
+
+        Some(record)
+    }
+
+    /// The moves played so far, oldest first.
+    pub fn history(&self) -> &[MoveRecord] {
+        &self.history
+    }
+
+    /// Walks the game forward from its starting position, yielding the game as it stood
+    /// at every ply: first the starting position, last a copy of the current one.
+    pub fn replay(&self) -> impl Iterator<Item = Game> + '_ {
+        let mut game = Game {
             board: self.board,
-            positions: self.positions.clone(),
+            positions: HashMap::new(),
+            history: Vec::with_capacity(self.history.len()),
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            declared: None,
+            draw_rules: self.draw_rules,
+            chess960: self.chess960,
+            variant: self.variant,
+            checks: self.checks,
+            pockets: self.pockets,
+            antichess: self.antichess,
+        };
+        if let Some(first) = self.history.first() {
+            game.board = first.board_before;
+            game.chess960 = first.chess960_before;
+            game.checks = first.checks_before;
+            game.pockets = first.pockets_before;
+            game.antichess = first.antichess_before;
+            game.halfmove_clock = first.halfmove_clock;
+            game.fullmove_number = first.fullmove_number;
+        }
+        game.increment_position_count();
+
+        let mut moves = self.history.iter();
+        std::iter::successors(Some(game), move |prev| {
+            moves.next().map(|record| {
+                let mut next = prev.clone();
+                next.apply_move(record.mv);
+                next
+            })
         })
     }
 
@@ -53,120 +519,313 @@ impl Game {
         self.positions.values().any(|&count| count >= 3)
     }
 
+    /// Plies since the last capture or pawn move.
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    /// Fifty moves by each side without a capture or pawn move: a draw either player may claim.
+    pub fn is_fifty_move_draw(&self) -> bool {
+        self.halfmove_clock >= 100
+    }
+
+    /// Seventy-five moves by each side without a capture or pawn move: an automatic draw.
+    pub fn is_seventy_five_move_draw(&self) -> bool {
+        self.halfmove_clock >= 150
+    }
+
+    pub fn is_fivefold_repetition(&self) -> bool {
+        self.positions.values().any(|&count| count >= 5)
+    }
 
     pub fn is_terminal(&self) -> bool {
-        // If threefold repetition detected, it's terminal (draw)
-        if self.is_threefold_repetition() ||
-            has_insufficient_material(&self.board) {
-            return true;
-        }
+        self.outcome().is_some()
+    }
 
-        let status = self.board.status();
-        if status != chess::BoardStatus::Ongoing {
-            return true;
+    pub fn draw_rules(&self) -> DrawRules {
+        self.draw_rules
+    }
+
+    /// Chooses whether threefold repetition and the fifty-move rule end the game
+    /// immediately (the default) or only when claimed.
+    pub fn set_draw_rules(&mut self, rules: DrawRules) {
+        self.draw_rules = rules;
+    }
+
+    /// The draw the side to move could claim right now, if any: the current position has
+    /// occurred three times, or fifty moves have passed without a capture or pawn move.
+    pub fn claimable_draw(&self) -> Option<Termination> {
+        if self.outcome().is_some() {
+            None
+        } else if self.positions.get(&self.get_hash()).is_some_and(|&count| count >= 3) {
+            Some(Termination::ThreefoldRepetition)
+        } else if self.is_fifty_move_draw() {
+            Some(Termination::FiftyMoveRule)
+        } else {
+            None
         }
+    }
 
-        // Check if no legal moves and not in check => stalemate
-        if MoveGen::new_legal(&self.board).count() == 0 {
-            return true
+    /// Claims a threefold repetition or fifty-move draw, ending the game.
+    pub fn claim_draw(&mut self) -> Result<GameOutcome, Error> {
+        if self.is_terminal() {
+            return Err(Error::NoDrawToClaim);
         }
+        let termination = self.claimable_draw().ok_or(Error::NoDrawToClaim)?;
 
-        false
+        let outcome = GameOutcome::new(GameResult::Draw, termination);
+        self.declared = Some(outcome);
+        Ok(outcome)
     }
 
-    pub fn legal_moves(&self) -> Vec<String> {
+    /// `player` resigns, handing the game to the opponent.
+    pub fn resign(&mut self, player: Player) {
+        self.declared = Some(GameOutcome::new(winner(player.opponent()), Termination::Resignation));
+    }
+
+    /// `player` ran out of time, handing the game to the opponent.
+    pub fn lose_on_time(&mut self, player: Player) {
+        self.declared = Some(GameOutcome::new(winner(player.opponent()), Termination::Timeout));
+    }
+
+    /// Both players agreed to a draw.
+    pub fn agree_draw(&mut self) {
+        self.declared = Some(GameOutcome::new(GameResult::Draw, Termination::Agreement));
+    }
+
+    /// Ends the game with a result decided outside the rules, e.g. by a match runner
+    /// stopping a clearly won or drawn self-play game early.
+    pub fn adjudicate(&mut self, result: GameResult) {
+        self.declared = Some(GameOutcome::new(result, Termination::Adjudication));
+    }
+
+    pub fn legal_moves(&self) -> Vec<Move> {
         if self.is_terminal() {
             return Vec::new();
         }
-        // Generate all legal moves from current position
-        let movegen = MoveGen::new_legal(&self.board);
-        movegen.map(|m| m.to_string()).collect()
+        self.generate_moves()
     }
 
-    fn parse_move(&self, move_str: &str) -> Result<ChessMove, String> {
-        if move_str.len() < 4 {
-            return Err("Move string too short".to_string());
+    /// Every legal move in the position, whether or not the game is already over.
+    fn generate_moves(&self) -> Vec<Move> {
+        if let Some(position) = &self.antichess {
+            return position.moves().into_iter().map(Into::into).collect();
         }
+        let mut moves: Vec<Move> = MoveGen::new_legal(&self.board).map(Into::into).collect();
+        moves.extend(self.castling_moves().into_iter().map(Into::<Move>::into));
+        moves.extend(self.drop_moves());
+        if self.variant != Variant::Standard {
+            moves.retain(|&mv| self.variant_allows(mv));
+        }
+        moves
+    }
 
-        let from_str = &move_str[0..2];
-        let to_str = &move_str[2..4];
-
-        let from_square = Square::from_str(from_str)
-            .map_err(|_| format!("Invalid from-square: {}", from_str))?;
-        let to_square = Square::from_str(to_str)
-            .map_err(|_| format!("Invalid to-square: {}", to_str))?;
+    fn is_legal(&self, mv: Move) -> bool {
+        if self.antichess.is_some() {
+            return self.generate_moves().contains(&mv);
+        }
+        let legal = match mv.drop {
+            Some(piece) => self.is_legal_drop(piece, mv.to()),
+            None => self.board.legal(mv.mv) || self.castling_moves().contains(&mv.mv),
+        };
+        legal && self.variant_allows(mv)
+    }
 
-        let promotion_piece = if move_str.len() > 4 {
-            // The fifth character in the move_str might represent the promotion piece.
-            // For example, 'q' for queen, 'r' for rook, 'b' for bishop, 'n' for knight.
-            match &move_str[4..5] {
-                "q" => Some(Piece::Queen),
-                "r" => Some(Piece::Rook),
-                "b" => Some(Piece::Bishop),
-                "n" => Some(Piece::Knight),
-                _ => return Err(format!("Invalid promotion piece: {}", &move_str[4..5])),
-            }
+    /// The board once `mv`, a legal move, is played.
+    fn board_after(&self, mv: Move) -> Board {
+        if let Some(piece) = mv.drop {
+            self.dropped_board(piece, mv.to())
+        } else if self.is_chess960() && self.castling_side(mv.mv).is_some() {
+            self.castled_board(mv.mv)
         } else {
-            None
-        };
+            self.board.make_move_new(mv.mv)
+        }
+    }
+
+    /// The piece on `sq`, from whichever board holds the position.
+    fn piece_on(&self, sq: Square) -> Option<Piece> {
+        match &self.antichess {
+            Some(position) => position.piece_on(sq),
+            None => self.board.piece_on(sq),
+        }
+    }
 
-        let mv = ChessMove::new(from_square, to_square, promotion_piece);
-        if self.board.legal(mv) {
+    fn color_on(&self, sq: Square) -> Option<Color> {
+        match &self.antichess {
+            Some(position) => position.color_on(sq),
+            None => self.board.color_on(sq),
+        }
+    }
+
+    /// Every piece of type `piece`, of either colour.
+    fn pieces(&self, piece: Piece) -> BitBoard {
+        match &self.antichess {
+            Some(position) => position.pieces(piece),
+            None => *self.board.pieces(piece),
+        }
+    }
+
+    fn color_combined(&self, color: Color) -> BitBoard {
+        match &self.antichess {
+            Some(position) => position.color_combined(color),
+            None => *self.board.color_combined(color),
+        }
+    }
+
+    fn parse_move(&self, move_str: &str) -> Result<Move, Error> {
+        let mv: Move = move_str.parse()?;
+        self.check_promotion(mv)?;
+        if self.is_legal(mv) {
             Ok(mv)
         } else {
-            Err(format!("Illegal move: {}", move_str))
+            Err(Error::IllegalMove(move_str.to_string()))
         }
     }
 
-    pub fn current_player(&self) -> &str {
-        if self.board.side_to_move() == chess::Color::White {
-            "White"
-        } else {
-            "Black"
+    /// Promoting to a king parses everywhere but is only a move in Antichess.
+    fn check_promotion(&self, mv: Move) -> Result<(), Error> {
+        match mv.promotion() {
+            Some(Piece::King) if self.variant != Variant::Antichess => Err(Error::BadPromotion("k".to_string())),
+            _ => Ok(()),
         }
     }
 
-    pub fn from_fen(fen: &str) -> Result<Self, String> {
-        match fen.parse::<Board>() {
+    pub fn current_player(&self) -> Player {
+        match &self.antichess {
+            Some(position) => position.side_to_move().into(),
+            None => self.board.side_to_move().into(),
+        }
+    }
+
+    pub fn from_fen(fen: &str) -> Result<Self, Error> {
+        Game::from_fen_variant(fen, Variant::Standard)
+    }
+
+    /// Reads a position of the given variant. Three-check FENs carry check counters, either
+    /// as remaining checks after the en passant square (`3+3`) or as checks given at the end
+    /// (`+0+0`). Crazyhouse FENs carry the pockets after the placement (`.../RNBQKBNR[Qp]`)
+    /// and mark promoted pieces with `~`.
+    pub fn from_fen_variant(fen: &str, variant: Variant) -> Result<Self, Error> {
+        let placement: String;
+        let mut fields: Vec<&str> = fen.split_whitespace().collect();
+        let checks = match variant {
+            Variant::ThreeCheck => variant::take_check_counts(&mut fields).map_err(Error::InvalidFen)?,
+            _ => [0, 0],
+        };
+        let pockets = match (variant, fields.first()) {
+            (Variant::Crazyhouse, Some(field)) => {
+                let (board_placement, pockets) = crazyhouse::split_placement(field).map_err(Error::InvalidFen)?;
+                placement = board_placement;
+                fields[0] = &placement;
+                pockets
+            }
+            _ => Pockets::default(),
+        };
+
+        // chess::Board ignores the move counters, so read them here. Both are optional.
+        let halfmove_clock = match fields.get(4) {
+            Some(field) => field.parse::<u32>()
+                .map_err(|_| Error::InvalidFen(format!("bad halfmove clock '{}'", field)))?,
+            None => 0,
+        };
+        let fullmove_number = match fields.get(5) {
+            Some(field) => field.parse::<u32>()
+                .map_err(|_| Error::InvalidFen(format!("bad fullmove number '{}'", field)))?
+                .max(1),
+            None => 1,
+        };
+
+        // Castling rights chess::Board cannot hold (X-FEN and Shredder-FEN) make this a
+        // Chess960 game; the board is then read without them.
+        let mut board_fields = fields.clone();
+        let mut chess960 = None;
+        if let Some(field) = fields.get(2).filter(|_| variant != Variant::Antichess) {
+            board_fields[2] = "-";
+            if let Ok(board) = board_fields.join(" ").parse::<Board>() {
+                chess960 = chess960::parse_castling(&board, field).map_err(Error::InvalidFen)?;
+            }
+            if chess960.is_none() {
+                board_fields[2] = *field;
+            }
+        }
+
+        // Antichess positions may have any number of kings, which chess::Board rejects, so
+        // they are read separately and the board is left at its default.
+        let antichess = match variant {
+            Variant::Antichess => Some(AntichessBoard::from_fen(&fields).map_err(Error::InvalidFen)?),
+            _ => None,
+        };
+        let board = match antichess {
+            Some(_) => Ok(Board::default()),
+            None => board_fields.join(" ").parse::<Board>(),
+        };
+
+        match board {
             Ok(board) => {
                 let mut game = Game {
                     board,
                     positions: HashMap::new(),
+                    history: Vec::new(),
+                    halfmove_clock,
+                    fullmove_number,
+                    declared: None,
+                    draw_rules: DrawRules::default(),
+                    chess960,
+                    variant,
+                    checks,
+                    pockets,
+                    antichess,
                 };
                 game.increment_position_count();
                 Ok(game)
             },
-            Err(e) => Err(format!("Invalid FEN: {}", e))
+            Err(e) => Err(Error::InvalidFen(e.to_string()))
         }
     }
 
+    /// The current position as a six-field FEN. The en passant square is only written
+    /// when a pawn can actually make the capture.
+    pub fn to_fen(&self) -> String {
+        if let Some(position) = &self.antichess {
+            return format!("{} {} {}", position.fen(), self.halfmove_clock, self.fullmove_number);
+        }
+        // chess::Board prints placement, side and castling correctly, but its en passant
+        // field and counters cannot be trusted.
+        let board_fen = self.board.to_string();
+        let mut position: Vec<String> = board_fen.split(' ').take(3).map(String::from).collect();
+        position[2] = self.castling_fen(&position[2]);
+        if let Some(placement) = self.crazyhouse_placement() {
+            position[0] = placement;
+        }
+        // The board stores the square of the pawn that can be taken; FEN wants the square behind it.
+        let en_passant = self.board.en_passant()
+            .and_then(|sq| sq.forward(self.board.side_to_move()))
+            .map_or("-".to_string(), |sq| sq.to_string());
+        if let Some(checks) = self.check_counts_fen() {
+            position.push(en_passant);
+            position.push(checks);
+            return format!("{} {} {}", position.join(" "), self.halfmove_clock, self.fullmove_number);
+        }
+        format!("{} {} {} {}", position.join(" "), en_passant, self.halfmove_clock, self.fullmove_number)
+    }
+
+    /// The FEN fullmove number: 1 at the start and increased after every Black move.
+    pub fn fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
     fn increment_position_count(&mut self) {
-        let key = self.board.get_hash();
+        let key = self.get_hash();
         *self.positions.entry(key).or_insert(0) += 1;
     }
 
     pub fn encode(&self) -> Vec<f32> {
-        let mut encoded = Vec::with_capacity(8*8*6);
-
-        // Match Python indexing: row=0 = rank0 (a1 row), row=7 = rank7 (a8 row)
-        // column=0 = file a, column=7 = file h
-        for row in 0..8 {
-            for column in 0..8 {
-                let sq = chess::Square::make_square(
-                    chess::Rank::from_index(row),
-                    chess::File::from_index(column)
-                );
-                let piece_vec = self.encode_piece(sq);
-                encoded.extend_from_slice(&piece_vec);
-            }
-        }
-
-        encoded
+        self.encode_for(Encoding::Pieces, Perspective::White)
     }
 
     fn encode_piece(&self, sq: chess::Square) -> [f32; 6] {
-        if let Some(piece) = self.board.piece_on(sq) {
-            let color = self.board.color_on(sq).unwrap();
+        if let Some(piece) = self.piece_on(sq) {
+            let color = self.color_on(sq).unwrap();
             match piece {
                 chess::Piece::Pawn => {
                     if color == chess::Color::White { [1.0,0.0,0.0,0.0,0.0,0.0] }
@@ -198,38 +857,44 @@ impl Game {
         }
     }
 
-    pub fn get_game_result(&self) -> Option<GameResult> {
-        if !self.is_terminal() {
-            return None;
-        }
-
-        // Check for threefold repetition
-        if self.is_threefold_repetition() {
-            return Some(GameResult::Draw);
+    /// How and why the game ended, or `None` while it is still in progress.
+    pub fn outcome(&self) -> Option<GameOutcome> {
+        if self.declared.is_some() {
+            return self.declared;
         }
 
-        // Check for insufficient material
-        if has_insufficient_material(&self.board) {
-            return Some(GameResult::Draw);
+        if let Some(outcome) = self.variant_outcome() {
+            return Some(outcome);
         }
 
-        // If there are no legal moves
-        if MoveGen::new_legal(&self.board).count() == 0 {
-            // If the current player is in check, it's checkmate
-            if self.board.checkers().popcnt() > 0 {
-                // If White is in check, Black wins and vice versa
-                return Some(if self.board.side_to_move() == Color::White {
-                    GameResult::BlackWin
-                } else {
-                    GameResult::WhiteWin
-                });
+        // Mate and stalemate come first: a mate delivered on the hundredth ply still wins.
+        if self.generate_moves().is_empty() {
+            return Some(if self.checkers().popcnt() > 0 {
+                GameOutcome::new(winner(self.current_player().opponent()), Termination::Checkmate)
             } else {
-                // Stalemate
-                return Some(GameResult::Draw);
-            }
+                GameOutcome::new(GameResult::Draw, Termination::Stalemate)
+            });
         }
 
-        Some(GameResult::Draw) // Default to draw for other terminal positions
+        let immediate = self.draw_rules == DrawRules::Immediate;
+        let draw = if self.is_fivefold_repetition() {
+            Termination::FivefoldRepetition
+        } else if immediate && self.is_threefold_repetition() {
+            Termination::ThreefoldRepetition
+        } else if self.is_seventy_five_move_draw() {
+            Termination::SeventyFiveMoveRule
+        } else if immediate && self.is_fifty_move_draw() {
+            Termination::FiftyMoveRule
+        } else if self.variant_insufficient_material() {
+            Termination::InsufficientMaterial
+        } else {
+            return None;
+        };
+        Some(GameOutcome::new(GameResult::Draw, draw))
+    }
+
+    pub fn get_game_result(&self) -> Option<GameResult> {
+        self.outcome().map(|outcome| outcome.result)
     }
 
     pub fn result_value(&self) -> f32 {
@@ -245,6 +910,34 @@ impl Game {
 
 }
 
+fn winner(player: Player) -> GameResult {
+    match player {
+        Player::White => GameResult::WhiteWin,
+        Player::Black => GameResult::BlackWin,
+    }
+}
+
+/// The piece `mv` takes off the board, if any. En passant captures land on an empty square,
+/// and Chess960 castling onto the king's own rook takes nothing.
+fn captured_piece(board: &Board, mv: ChessMove) -> Option<Piece> {
+    let dest = mv.get_dest();
+    match board.piece_on(dest) {
+        Some(_) if board.color_on(dest) == Some(board.side_to_move()) => None,
+        Some(piece) => Some(piece),
+        None if board.piece_on(mv.get_source()) == Some(Piece::Pawn)
+            && mv.get_source().get_file() != dest.get_file() => Some(Piece::Pawn),
+        None => None,
+    }
+}
+
+/// Scrambles a small number into a hash key, for game state the board's own hash misses.
+fn splitmix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
 fn has_insufficient_material(board: &chess::Board) -> bool {
     use chess::Piece;
     // Count pieces by type
@@ -332,6 +1025,9 @@ fn has_insufficient_material(board: &chess::Board) -> bool {
 mod tests {
     use super::*;
 
+    fn mv(uci: &str) -> Move {
+        uci.parse().expect("test move should parse")
+    }
 
     #[test]
     fn test_initial_moves_count() {
@@ -347,7 +1043,7 @@ mod tests {
         let initial_moves = game.legal_moves();
 
         // "e2e4" is a known standard chess opening move.
-        assert!(initial_moves.contains(&"e2e4".to_string()), "Initial moves must include 'e2e4'.");
+        assert!(initial_moves.contains(&mv("e2e4")), "Initial moves must include 'e2e4'.");
 
         // Make the move and get a new game state.
         game = game.make_move("e2e4").expect("Move e2e4 should be legal and succeed.");
@@ -358,6 +1054,36 @@ mod tests {
         assert_ne!(initial_moves, new_moves, "Legal moves should differ after making a move.");
     }
 
+    #[test]
+    fn test_typed_moves() {
+        let promotion = mv("e7e8q");
+        assert_eq!(promotion.source(), Square::E7);
+        assert_eq!(promotion.to(), Square::E8);
+        assert_eq!(promotion.promotion(), Some(Piece::Queen));
+        assert_eq!(promotion.to_string(), "e7e8q");
+        assert_eq!(mv("g1f3").to_string(), "g1f3");
+        assert_eq!(Move::from(ChessMove::new(Square::E7, Square::E8, Some(Piece::Queen))), promotion);
+
+        assert!(matches!("e2".parse::<Move>(), Err(Error::MalformedMove(_))));
+        assert!(matches!("e2e9".parse::<Move>(), Err(Error::MalformedMove(_))));
+        assert!(matches!("e7e8x".parse::<Move>(), Err(Error::BadPromotion(p)) if p == "x"));
+        // Promoting to a king is valid syntax for Antichess, but never legal in chess.
+        assert_eq!(mv("e7e8k").promotion(), Some(Piece::King));
+        let mut promoting = Game::from_fen("2k5/4P3/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(matches!(promoting.make_move("e7e8k"), Err(Error::BadPromotion(p)) if p == "k"));
+        assert!(matches!(promoting.play_move(mv("e7e8k")), Err(Error::BadPromotion(_))));
+        assert_eq!(Game::new().make_move("e2e5").unwrap_err().to_string(), "Illegal move: e2e5");
+
+        let mut game = Game::new();
+        let e4 = game.legal_moves().into_iter().find(|m| m.to() == Square::E4).unwrap();
+        game.play_move(e4).expect("A generated move should be playable");
+        assert_eq!(game.current_player(), Player::Black);
+        assert_eq!(game.history()[0].mv, e4);
+        assert!(game.play_move(mv("e2e4")).is_err(), "It is Black's move.");
+        assert_eq!(Player::Black.opponent(), Player::White);
+        assert_eq!(Player::White.to_string(), "White");
+    }
+
     #[test]
     fn test_checkmate_detection() {
         let mut game = Game::new();
@@ -385,24 +1111,24 @@ mod tests {
 
         // Initial position: White to move.
         let current_player = game.current_player();
-        assert_eq!(current_player, "White", "Game should start with White to move.");
+        assert_eq!(current_player, Player::White, "Game should start with White to move.");
 
         // Make one White move, e.g. "e2e4".
-        assert!(initial_moves.contains(&"e2e4".to_string()), "Initial moves must include 'e2e4'.");
+        assert!(initial_moves.contains(&mv("e2e4")), "Initial moves must include 'e2e4'.");
         game = game.make_move("e2e4").expect("e2e4 should be legal");
 
         // Now it should be Black's turn.
         let current_player = game.current_player();
-        assert_eq!(current_player, "Black", "After White moves, it should be Black's turn.");
+        assert_eq!(current_player, Player::Black, "After White moves, it should be Black's turn.");
 
         // Make one Black move, e.g. "e7e5".
         let moves_after_white = game.legal_moves();
-        assert!(moves_after_white.contains(&"e7e5".to_string()), "Moves must include 'e7e5' for Black.");
+        assert!(moves_after_white.contains(&mv("e7e5")), "Moves must include 'e7e5' for Black.");
         game = game.make_move("e7e5").expect("e7e5 should be legal");
 
         // Now it should be White's turn again.
         let current_player = game.current_player();
-        assert_eq!(current_player, "White", "After Black moves, it should be White's turn again.");
+        assert_eq!(current_player, Player::White, "After Black moves, it should be White's turn again.");
     }
 
     #[test]
@@ -412,7 +1138,7 @@ mod tests {
             .expect("Should be able to create a board from a legal stalemate FEN");
 
         // Print the initial game state for debugging
-        println!("{:?}", game);
+        println!("{}", game);
 
         assert!(game.is_terminal(), "Game should be terminal due to stalemate.");
         let legal_moves = game.legal_moves();
@@ -423,7 +1149,7 @@ mod tests {
     fn test_insufficient_material() {
         let fen = "8/8/8/8/8/6K1/8/2k5 w - - 0 1";
         let game = Game::from_fen(fen).expect("Should parse fen for kings only");
-        println!("{:?}", game);
+        println!("{}", game);
         // Now you must ensure your `is_terminal()` recognizes insufficient material as a terminal state.
         // Check the crate's `BoardStatus` enum. Insufficient material should lead to a `Draw` status.
         assert!(game.is_terminal(), "Game should be terminal due to insufficient material.");
@@ -484,10 +1210,184 @@ mod tests {
 
         assert!(game.is_terminal(), "Game should be terminal due to the fifty-move rule draw.");
         let legal_moves = game.legal_moves();
-        println!("{:?}", game);
+        println!("{}", game);
         assert_eq!(legal_moves.len(), 0, "No legal moves should be available because the game is a draw.");
     }
 
+    #[test]
+    fn test_fifty_move_rule_without_repetition() {
+        // Rooks shuffling without repeating a position, one quiet move short of the limit.
+        let fen = "4k3/8/8/8/8/8/r7/4K2R w - - 99 80";
+        let mut game = Game::from_fen(fen).expect("Should parse fen");
+        assert_eq!(game.halfmove_clock(), 99);
+        assert!(!game.is_terminal(), "99 quiet plies are not yet a draw.");
+
+        game.make_move("h1h7").expect("Move should be legal");
+        assert_eq!(game.halfmove_clock(), 100);
+        assert!(!game.is_threefold_repetition(), "No position has repeated.");
+        assert!(game.is_fifty_move_draw());
+        assert!(!game.is_seventy_five_move_draw());
+        assert!(game.is_terminal(), "Game should be terminal due to the fifty-move rule.");
+        assert_eq!(game.get_game_result(), Some(GameResult::Draw));
+        assert_eq!(game.legal_moves().len(), 0);
+
+        // A capture on the hundredth ply resets the clock instead.
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/r7/4K2R w - - 98 80").unwrap();
+        game.make_move("h1h2").unwrap();
+        assert!(!game.is_terminal());
+        game.make_move("a2h2").unwrap();
+        assert_eq!(game.halfmove_clock(), 0, "Captures reset the halfmove clock.");
+    }
+
+    #[test]
+    fn test_checkmate_beats_fifty_move_rule() {
+        let fen = "4k3/R7/8/8/8/8/8/4K2R w - - 99 80";
+        let mut game = Game::from_fen(fen).expect("Should parse fen");
+        game.make_move("h1h8").expect("Rh8 is mate");
+        assert_eq!(game.halfmove_clock(), 100);
+        assert_eq!(game.get_game_result(), Some(GameResult::WhiteWin));
+    }
+
+    #[test]
+    fn test_seventy_five_move_rule() {
+        let fen = "4k3/8/8/8/8/8/r7/4K2R w - - 149 120";
+        let mut game = Game::from_fen(fen).expect("Should parse fen");
+        assert!(game.is_fifty_move_draw());
+        assert!(!game.is_seventy_five_move_draw());
+
+        game.make_move("h1h7").unwrap();
+        assert!(game.is_seventy_five_move_draw());
+        assert!(game.is_terminal());
+        assert_eq!(game.get_game_result(), Some(GameResult::Draw));
+    }
+
+    #[test]
+    fn test_outcome_reports_termination() {
+        let mut game = Game::new();
+        assert_eq!(game.outcome(), None);
+        for mov in ["f2f3", "e7e5", "g2g4", "d8h4"] {
+            game.make_move(mov).unwrap();
+        }
+        assert_eq!(game.outcome(), Some(GameOutcome::new(GameResult::BlackWin, Termination::Checkmate)));
+
+        let game = Game::from_fen("7k/5Q2/8/8/8/8/8/7K b - - 0 1").unwrap();
+        assert_eq!(game.outcome(), Some(GameOutcome::new(GameResult::Draw, Termination::Stalemate)));
+
+        let game = Game::from_fen("8/8/8/8/8/6K1/8/2k5 w - - 0 1").unwrap();
+        assert_eq!(game.outcome().unwrap().termination, Termination::InsufficientMaterial);
+
+        let mut game = Game::new();
+        for mov in ["g1f3", "b8c6", "f3g1", "c6b8", "g1f3", "b8c6", "f3g1", "c6b8"] {
+            game.make_move(mov).unwrap();
+        }
+        assert_eq!(game.outcome().unwrap().termination, Termination::ThreefoldRepetition);
+
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/r7/4K2R w - - 99 80").unwrap();
+        game.make_move("h1h7").unwrap();
+        assert_eq!(game.outcome().unwrap().termination, Termination::FiftyMoveRule);
+
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/r7/4K2R w - - 149 120").unwrap();
+        game.make_move("h1h7").unwrap();
+        assert_eq!(game.outcome().unwrap().termination, Termination::SeventyFiveMoveRule);
+    }
+
+    #[test]
+    fn test_declared_outcomes() {
+        let mut game = Game::new();
+        game.make_move("e2e4").unwrap();
+        game.resign(Player::Black);
+        assert!(game.is_terminal());
+        assert!(game.legal_moves().is_empty());
+        assert_eq!(game.outcome(), Some(GameOutcome::new(GameResult::WhiteWin, Termination::Resignation)));
+        assert_eq!(game.result_value(), 1.0);
+
+        game.lose_on_time(Player::White);
+        assert_eq!(game.outcome(), Some(GameOutcome::new(GameResult::BlackWin, Termination::Timeout)));
+
+        game.agree_draw();
+        assert_eq!(game.outcome(), Some(GameOutcome::new(GameResult::Draw, Termination::Agreement)));
+
+        game.adjudicate(GameResult::WhiteWin);
+        assert_eq!(game.outcome(), Some(GameOutcome::new(GameResult::WhiteWin, Termination::Adjudication)));
+
+        // Taking a move back withdraws the declared result.
+        game.undo_move().unwrap();
+        assert_eq!(game.outcome(), None);
+        assert_eq!(game.legal_moves().len(), 20);
+    }
+
+    #[test]
+    fn test_outcome_display() {
+        let outcome = GameOutcome::new(GameResult::Draw, Termination::ThreefoldRepetition);
+        assert_eq!(outcome.to_string(), "Draw by threefold repetition");
+        let outcome = GameOutcome::new(GameResult::BlackWin, Termination::Timeout);
+        assert_eq!(outcome.to_string(), "Black wins by timeout");
+        assert_eq!(Termination::Timeout.pgn_tag(), "time forfeit");
+        assert_eq!(Termination::Stalemate.pgn_tag(), "normal");
+    }
+
+    #[test]
+    fn test_claimable_threefold_repetition() {
+        let mut game = Game::new();
+        game.set_draw_rules(DrawRules::Claimable);
+        for mov in ["g1f3", "b8c6", "f3g1", "c6b8", "g1f3", "b8c6", "f3g1"] {
+            game.make_move(mov).unwrap();
+        }
+        assert!(matches!(game.claim_draw(), Err(Error::NoDrawToClaim)), "The position has only occurred twice.");
+
+        game.make_move("c6b8").unwrap();
+        assert!(game.is_threefold_repetition());
+        assert!(!game.is_terminal(), "A threefold repetition has to be claimed.");
+        assert_eq!(game.legal_moves().len(), 20);
+        assert_eq!(game.claimable_draw(), Some(Termination::ThreefoldRepetition));
+
+        // Once play moves on, the repetition can no longer be claimed.
+        game.make_move("e2e4").unwrap();
+        assert_eq!(game.claimable_draw(), None);
+        game.undo_move().unwrap();
+
+        let outcome = game.claim_draw().expect("The claim should succeed");
+        assert_eq!(outcome, GameOutcome::new(GameResult::Draw, Termination::ThreefoldRepetition));
+        assert!(game.is_terminal());
+        assert!(game.claim_draw().is_err(), "A finished game cannot be claimed again.");
+    }
+
+    #[test]
+    fn test_fivefold_repetition_is_automatic() {
+        let mut game = Game::new();
+        game.set_draw_rules(DrawRules::Claimable);
+        for _ in 0..4 {
+            for mov in ["g1f3", "b8c6", "f3g1", "c6b8"] {
+                assert!(!game.is_terminal());
+                game.make_move(mov).unwrap();
+            }
+        }
+        assert!(game.is_fivefold_repetition());
+        assert_eq!(game.outcome(), Some(GameOutcome::new(GameResult::Draw, Termination::FivefoldRepetition)));
+        assert!(game.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn test_claimable_fifty_and_automatic_seventy_five_move_rules() {
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/r7/4K2R w - - 99 80").unwrap();
+        game.set_draw_rules(DrawRules::Claimable);
+        game.make_move("h1h7").unwrap();
+        assert!(!game.is_terminal(), "The fifty-move rule has to be claimed.");
+        assert_eq!(game.claimable_draw(), Some(Termination::FiftyMoveRule));
+
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/r7/4K2R w - - 149 120").unwrap();
+        game.set_draw_rules(DrawRules::Claimable);
+        game.make_move("h1h7").unwrap();
+        assert_eq!(game.outcome().unwrap().termination, Termination::SeventyFiveMoveRule);
+
+        // The default rules keep the old behaviour of ending the game straight away.
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/r7/4K2R w - - 99 80").unwrap();
+        assert_eq!(game.draw_rules(), DrawRules::Immediate);
+        game.make_move("h1h7").unwrap();
+        assert!(game.is_terminal());
+        assert!(game.claim_draw().is_err());
+    }
+
     #[test]
     fn test_en_passant() {
         let fen = "rnbqkbnr/ppppp1pp/8/5p2/4P3/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 1";
@@ -499,7 +1399,7 @@ mod tests {
 
         // En passant should be legal here: White pawn on e4 can capture on f5.
         let legal_moves = game.legal_moves();
-        assert!(legal_moves.contains(&"e4f5".to_string()), "En passant capture should be legal.");
+        assert!(legal_moves.contains(&mv("e4f5")), "En passant capture should be legal.");
 
         // Make the en passant capture:
         game = game.make_move("e4f5").expect("En passant should work.");
@@ -519,10 +1419,10 @@ mod tests {
         let legal_moves = game.legal_moves();
         //print the legal moves
         println!("{:?}", legal_moves);
-        assert!(legal_moves.contains(&"g7g8q".to_string()), "Promotion to queen should be legal.");
-        assert!(legal_moves.contains(&"g7g8r".to_string()), "Promotion to rook should be legal.");
-        assert!(legal_moves.contains(&"g7g8b".to_string()), "Promotion to bishop should be legal.");
-        assert!(legal_moves.contains(&"g7g8n".to_string()), "Promotion to knight should be legal.");
+        assert!(legal_moves.contains(&mv("g7g8q")), "Promotion to queen should be legal.");
+        assert!(legal_moves.contains(&mv("g7g8r")), "Promotion to rook should be legal.");
+        assert!(legal_moves.contains(&mv("g7g8b")), "Promotion to bishop should be legal.");
+        assert!(legal_moves.contains(&mv("g7g8n")), "Promotion to knight should be legal.");
 
         // Choose one promotion, say to a queen:
         game = game.make_move("g7g8q").expect("Pawn promotion to queen should be allowed.");
@@ -538,8 +1438,8 @@ mod tests {
 
         // In this position, White should be able to castle kingside (e1g1) and queenside (e1c1).
         let legal_moves = game.legal_moves();
-        assert!(legal_moves.contains(&"e1g1".to_string()), "White should be able to castle kingside.");
-        assert!(legal_moves.contains(&"e1c1".to_string()), "White should be able to castle queenside.");
+        assert!(legal_moves.contains(&mv("e1g1")), "White should be able to castle kingside.");
+        assert!(legal_moves.contains(&mv("e1c1")), "White should be able to castle queenside.");
 
         // Make a castling move, say kingside:
         game = game.make_move("e1g1").expect("Should be able to castle kingside");
@@ -556,8 +1456,8 @@ mod tests {
 
         // Initially, White should be able to castle kingside and queenside.
         let legal_moves = game.legal_moves();
-        assert!(legal_moves.contains(&"e1g1".to_string()), "White should be able to castle kingside initially.");
-        assert!(legal_moves.contains(&"e1c1".to_string()), "White should be able to castle queenside initially.");
+        assert!(legal_moves.contains(&mv("e1g1")), "White should be able to castle kingside initially.");
+        assert!(legal_moves.contains(&mv("e1c1")), "White should be able to castle queenside initially.");
 
         // Move the king forward and then back.
         // e1e2 is a legal move, and then we move back e2e1.
@@ -568,8 +1468,8 @@ mod tests {
 
         // Now check that castling rights are gone.
         let legal_moves_after = game.legal_moves();
-        assert!(!legal_moves_after.contains(&"e1g1".to_string()), "White should no longer be able to castle kingside after king has moved.");
-        assert!(!legal_moves_after.contains(&"e1c1".to_string()), "White should no longer be able to castle queenside after king has moved.");
+        assert!(!legal_moves_after.contains(&mv("e1g1")), "White should no longer be able to castle kingside after king has moved.");
+        assert!(!legal_moves_after.contains(&mv("e1c1")), "White should no longer be able to castle queenside after king has moved.");
     }
 
     */
@@ -606,6 +1506,129 @@ mod tests {
         assert_eq!(game.get_game_result(), Some(GameResult::Draw));
     }
 
+    #[test]
+    fn test_undo_move_restores_position() {
+        let mut game = Game::new();
+        let initial_moves = game.legal_moves();
+
+        game.make_move("e2e4").unwrap();
+        game.make_move("e7e5").unwrap();
+
+        let undone = game.undo_move().expect("There should be a move to undo");
+        assert_eq!(undone.mv.to_string(), "e7e5");
+        assert_eq!(game.current_player(), Player::Black, "Undoing Black's move gives Black the move again.");
+
+        game.undo_move().expect("There should be a second move to undo");
+        assert_eq!(game.get_hash(), Board::default().get_hash(), "Should be back at the starting position.");
+        assert_eq!(game.legal_moves(), initial_moves);
+        assert!(game.undo_move().is_none(), "Nothing left to undo at the start of the game.");
+        assert!(game.history().is_empty());
+    }
+
+    #[test]
+    fn test_undo_move_restores_repetition_counts() {
+        let mut game = Game::new();
+        for mov in ["g1f3", "b8c6", "f3g1", "c6b8", "g1f3", "b8c6", "f3g1", "c6b8"] {
+            game.make_move(mov).unwrap();
+        }
+        assert!(game.is_threefold_repetition());
+        assert_eq!(game.history().last().unwrap().repetitions, 3);
+
+        game.undo_move().unwrap();
+        assert!(!game.is_threefold_repetition(), "Undoing the repeating move should clear the repetition.");
+        assert!(!game.is_terminal());
+
+        game.make_move("c6b8").unwrap();
+        assert!(game.is_threefold_repetition(), "Replaying the move should repeat the position again.");
+    }
+
+    #[test]
+    fn test_history_records_captures() {
+        let fen = "rnbqkbnr/ppppp1pp/8/4P3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
+        let mut game = Game::from_fen(fen).expect("Should parse fen");
+
+        game.make_move("d7d5").unwrap();
+        game.make_move("e5d6").unwrap(); // en passant
+        game.make_move("c7d6").unwrap();
+
+        let captured: Vec<Option<Piece>> = game.history().iter().map(|r| r.captured).collect();
+        assert_eq!(captured, vec![None, Some(Piece::Pawn), Some(Piece::Pawn)]);
+
+        // Taking back the en passant capture puts the black pawn back on d5.
+        game.undo_move().unwrap();
+        game.undo_move().unwrap();
+        assert_eq!(game.board.piece_on(Square::D5), Some(Piece::Pawn));
+        assert_eq!(game.board.piece_on(Square::D6), None);
+    }
+
+    #[test]
+    fn test_replay_walks_every_ply() {
+        let mut game = Game::new();
+        for mov in ["e2e4", "e7e5", "g1f3", "b8c6"] {
+            game.make_move(mov).unwrap();
+        }
+
+        let positions: Vec<Game> = game.replay().collect();
+        assert_eq!(positions.len(), 5, "Replay should yield the start plus one game per move.");
+        assert_eq!(positions[0].get_hash(), Board::default().get_hash());
+        assert!(positions[0].history().is_empty());
+        for (ply, position) in positions.iter().enumerate() {
+            assert_eq!(position.history().len(), ply);
+        }
+
+        let last = positions.last().unwrap();
+        assert_eq!(last.get_hash(), game.get_hash());
+        assert_eq!(last.history(), game.history());
+    }
+
+    #[test]
+    fn test_fen_round_trip() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 12 40",
+            "4k3/8/8/8/8/8/8/4K2R b K - 99 150",
+        ];
+        for fen in fens {
+            let game = Game::from_fen(fen).expect("Should parse fen");
+            assert_eq!(game.to_fen(), fen);
+            assert_eq!(Game::from_fen(&game.to_fen()).unwrap().to_fen(), fen);
+        }
+
+        assert_eq!(Game::new().to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        // Counters are optional in the input and default to the start of the game.
+        assert_eq!(Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - -").unwrap().to_fen(), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        let error = Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - - x 1").unwrap_err();
+        assert!(matches!(error, Error::InvalidFen(_)));
+        assert_eq!(error.to_string(), "Invalid FEN: bad halfmove clock 'x'");
+        assert!(Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 y").is_err());
+    }
+
+    #[test]
+    fn test_fen_counters_follow_moves() {
+        let mut game = Game::from_fen("r3k3/8/8/8/8/8/4P3/R3K3 w Qq - 7 20").unwrap();
+
+        game.make_move("a1a7").unwrap(); // quiet rook move
+        assert_eq!(game.to_fen(), "r3k3/R7/8/8/8/8/4P3/4K3 b q - 8 20");
+        game.make_move("a8a7").unwrap(); // capture resets the clock
+        assert_eq!(game.to_fen(), "4k3/r7/8/8/8/8/4P3/4K3 w - - 0 21");
+        game.make_move("e1d1").unwrap();
+        game.make_move("a7a6").unwrap();
+        assert_eq!(game.to_fen(), "4k3/8/r7/8/8/8/4P3/3K4 w - - 2 22");
+        game.make_move("e2e4").unwrap(); // pawn move resets the clock
+        assert_eq!(game.to_fen(), "4k3/8/r7/8/4P3/8/8/3K4 b - - 0 22");
+
+        game.undo_move().unwrap();
+        game.undo_move().unwrap();
+        assert_eq!(game.to_fen(), "4k3/r7/8/8/8/8/4P3/3K4 b - - 1 21");
+
+        let start = game.replay().next().unwrap();
+        assert_eq!(start.to_fen(), "r3k3/8/8/8/8/8/4P3/R3K3 w Qq - 7 20");
+        assert_eq!(start.fullmove_number(), 20);
+    }
+
     #[test]
     fn test_encoding_initial_position() {
         // Standard initial chess position:
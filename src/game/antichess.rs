@@ -0,0 +1,362 @@
+//! Antichess (losing chess): captures are compulsory, the king is an ordinary piece that can
+//! be captured or promoted to, there is no castling, and a player wins by losing all of their
+//! pieces or by having no move left. chess::Board insists on exactly one king per side, so
+//! the position lives in `AntichessBoard` and has its own move generation.
+
+use chess::{get_bishop_moves, get_king_moves, get_knight_moves, get_pawn_attacks, get_pawn_quiets, get_rook_moves,
+            BitBoard, ChessMove, Color, File, Piece, Rank, Square, EMPTY};
+use super::{winner, Game, GameOutcome, Termination};
+
+/// What a pawn may promote to; in Antichess that includes the king.
+const PROMOTIONS: [Piece; 5] = [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight, Piece::King];
+
+const PIECES: [Piece; 6] = [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen, Piece::King];
+
+/// An Antichess position: any number of kings, and no checks, pins or castling.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct AntichessBoard {
+    pieces: [BitBoard; 6],
+    colors: [BitBoard; 2],
+    side_to_move: Color,
+    /// The square behind a pawn that just moved two squares, kept only while a pawn can
+    /// actually take it en passant.
+    en_passant: Option<Square>,
+}
+
+impl AntichessBoard {
+    /// Reads the placement, side to move and en passant fields of a FEN. Castling does not
+    /// exist in Antichess, so the castling field is ignored.
+    pub(super) fn from_fen(fields: &[&str]) -> Result<Self, String> {
+        let placement = fields.first().ok_or("missing piece placement")?;
+        let bad_placement = || format!("bad Antichess placement '{}'", placement);
+
+        let mut position = AntichessBoard {
+            pieces: [EMPTY; 6],
+            colors: [EMPTY; 2],
+            side_to_move: Color::White,
+            en_passant: None,
+        };
+        let ranks: Vec<&str> = placement.split('/').collect();
+        if ranks.len() != 8 {
+            return Err(bad_placement());
+        }
+        for (row, rank_text) in ranks.iter().enumerate() {
+            let rank = Rank::from_index(7 - row);
+            let mut file = 0;
+            for c in rank_text.chars() {
+                if let Some(empty) = c.to_digit(10) {
+                    file += empty as usize;
+                    continue;
+                }
+                let piece = PIECES.into_iter()
+                    .find(|piece| piece.to_string(Color::Black) == c.to_ascii_lowercase().to_string())
+                    .ok_or_else(bad_placement)?;
+                if file >= 8 || (piece == Piece::Pawn && matches!(rank, Rank::First | Rank::Eighth)) {
+                    return Err(bad_placement());
+                }
+                let color = if c.is_ascii_uppercase() { Color::White } else { Color::Black };
+                position.xor(piece, Square::make_square(rank, File::from_index(file)), color);
+                file += 1;
+            }
+            if file != 8 {
+                return Err(bad_placement());
+            }
+        }
+
+        position.side_to_move = match fields.get(1) {
+            Some(&"w") | None => Color::White,
+            Some(&"b") => Color::Black,
+            Some(other) => return Err(format!("bad side to move '{}'", other)),
+        };
+        position.en_passant = match fields.get(3) {
+            Some(&"-") | None => None,
+            Some(field) => {
+                let sq = field.parse::<Square>()
+                    .map_err(|_| format!("bad en passant square '{}'", field))?;
+                position.capturable_en_passant(sq)
+            }
+        };
+        Ok(position)
+    }
+
+    pub(super) fn piece_on(&self, sq: Square) -> Option<Piece> {
+        let bb = BitBoard::from_square(sq);
+        PIECES.into_iter().find(|piece| self.pieces[piece.to_index()] & bb != EMPTY)
+    }
+
+    pub(super) fn color_on(&self, sq: Square) -> Option<Color> {
+        let bb = BitBoard::from_square(sq);
+        [Color::White, Color::Black].into_iter().find(|color| self.colors[color.to_index()] & bb != EMPTY)
+    }
+
+    pub(super) fn pieces(&self, piece: Piece) -> BitBoard {
+        self.pieces[piece.to_index()]
+    }
+
+    pub(super) fn color_combined(&self, color: Color) -> BitBoard {
+        self.colors[color.to_index()]
+    }
+
+    pub(super) fn side_to_move(&self) -> Color {
+        self.side_to_move
+    }
+
+    /// The square behind a pawn that can be taken en passant.
+    pub(super) fn en_passant(&self) -> Option<Square> {
+        self.en_passant
+    }
+
+    /// Every legal move: the captures if there are any, since taking is compulsory, and
+    /// otherwise the quiet moves.
+    pub(super) fn moves(&self) -> Vec<ChessMove> {
+        let us = self.side_to_move;
+        let ours = self.colors[us.to_index()];
+        let theirs = self.colors[(!us).to_index()];
+        let occupied = ours | theirs;
+
+        let mut captures = Vec::new();
+        let mut quiets = Vec::new();
+        for from in ours {
+            let piece = self.piece_on(from).expect("an occupied square holds a piece");
+            if piece == Piece::Pawn {
+                let en_passant = self.en_passant.map_or(EMPTY, BitBoard::from_square);
+                push_pawn_moves(from, get_pawn_attacks(from, us, theirs | en_passant), &mut captures);
+                push_pawn_moves(from, get_pawn_quiets(from, us, occupied), &mut quiets);
+                continue;
+            }
+
+            let targets = match piece {
+                Piece::Knight => get_knight_moves(from),
+                Piece::Bishop => get_bishop_moves(from, occupied),
+                Piece::Rook => get_rook_moves(from, occupied),
+                Piece::Queen => get_bishop_moves(from, occupied) | get_rook_moves(from, occupied),
+                _ => get_king_moves(from),
+            };
+            captures.extend((targets & theirs).map(|to| ChessMove::new(from, to, None)));
+            quiets.extend((targets & !occupied).map(|to| ChessMove::new(from, to, None)));
+        }
+
+        if captures.is_empty() { quiets } else { captures }
+    }
+
+    /// The piece `mv` takes off the board, if any.
+    pub(super) fn captured_piece(&self, mv: ChessMove) -> Option<Piece> {
+        match self.piece_on(mv.get_dest()) {
+            Some(piece) => Some(piece),
+            None if self.is_en_passant(mv) => Some(Piece::Pawn),
+            None => None,
+        }
+    }
+
+    /// The position once `mv`, a legal move, is played.
+    pub(super) fn after(&self, mv: ChessMove) -> Self {
+        let us = self.side_to_move;
+        let (from, to) = (mv.get_source(), mv.get_dest());
+        let piece = self.piece_on(from).expect("a legal move starts on a piece");
+
+        let mut next = *self;
+        next.xor(piece, from, us);
+        if let Some(captured) = self.piece_on(to) {
+            next.xor(captured, to, !us);
+        } else if self.is_en_passant(mv) {
+            next.xor(Piece::Pawn, to.ubackward(us), !us);
+        }
+        next.xor(mv.get_promotion().unwrap_or(piece), to, us);
+
+        next.side_to_move = !us;
+        next.en_passant = None;
+        if piece == Piece::Pawn && from.get_rank().to_index().abs_diff(to.get_rank().to_index()) == 2 {
+            next.en_passant = next.capturable_en_passant(from.uforward(us));
+        }
+        next
+    }
+
+    /// Hash of the position, built from `splitmix` like the game's other keys.
+    pub(super) fn key(&self) -> u64 {
+        let mut key = match self.side_to_move {
+            Color::White => 0,
+            Color::Black => super::splitmix(0xAC00),
+        };
+        for piece in PIECES {
+            for color in [Color::White, Color::Black] {
+                let slot = (piece.to_index() * 2 + color.to_index()) as u64;
+                for sq in self.pieces[piece.to_index()] & self.colors[color.to_index()] {
+                    key ^= super::splitmix(0xAC_0000 + slot * 64 + sq.to_int() as u64);
+                }
+            }
+        }
+        if let Some(sq) = self.en_passant {
+            key ^= super::splitmix(0xAD00 + sq.to_int() as u64);
+        }
+        key
+    }
+
+    /// The first four FEN fields: placement, side to move, castling (always `-`) and en passant.
+    pub(super) fn fen(&self) -> String {
+        let mut placement = String::new();
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                let sq = Square::make_square(Rank::from_index(rank), File::from_index(file));
+                match (self.piece_on(sq), self.color_on(sq)) {
+                    (Some(piece), Some(color)) => {
+                        if empty > 0 {
+                            placement.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        placement.push_str(&piece.to_string(color));
+                    }
+                    _ => empty += 1,
+                }
+            }
+            if empty > 0 {
+                placement.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                placement.push('/');
+            }
+        }
+
+        let side = if self.side_to_move == Color::White { "w" } else { "b" };
+        let en_passant = self.en_passant.map_or("-".to_string(), |sq| sq.to_string());
+        format!("{} {} - {}", placement, side, en_passant)
+    }
+
+    fn is_en_passant(&self, mv: ChessMove) -> bool {
+        Some(mv.get_dest()) == self.en_passant && self.piece_on(mv.get_source()) == Some(Piece::Pawn)
+    }
+
+    /// `sq` if a pawn of the side to move can take en passant onto it.
+    fn capturable_en_passant(&self, sq: Square) -> Option<Square> {
+        let us = self.side_to_move;
+        let our_pawns = self.pieces[Piece::Pawn.to_index()] & self.colors[us.to_index()];
+        (get_pawn_attacks(sq, !us, our_pawns) != EMPTY).then_some(sq)
+    }
+
+    fn xor(&mut self, piece: Piece, sq: Square, color: Color) {
+        let bb = BitBoard::from_square(sq);
+        self.pieces[piece.to_index()] ^= bb;
+        self.colors[color.to_index()] ^= bb;
+    }
+}
+
+/// Adds pawn moves from `from` to each of `targets`, with every promotion on the last rank.
+fn push_pawn_moves(from: Square, targets: BitBoard, moves: &mut Vec<ChessMove>) {
+    for to in targets {
+        if matches!(to.get_rank(), Rank::First | Rank::Eighth) {
+            moves.extend(PROMOTIONS.into_iter().map(|piece| ChessMove::new(from, to, Some(piece))));
+        } else {
+            moves.push(ChessMove::new(from, to, None));
+        }
+    }
+}
+
+impl Game {
+    /// Antichess ends when a player has lost all their pieces or has no move left, and
+    /// either way that player wins.
+    pub(super) fn antichess_outcome(&self) -> Option<GameOutcome> {
+        let position = self.antichess.as_ref()?;
+        if let Some(color) = [Color::White, Color::Black].into_iter()
+            .find(|color| position.colors[color.to_index()] == EMPTY)
+        {
+            return Some(GameOutcome::new(winner(color.into()), Termination::LostAllPieces));
+        }
+        if position.moves().is_empty() {
+            return Some(GameOutcome::new(winner(self.current_player()), Termination::Stalemate));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameResult, Move, PerftRules, Player, Variant};
+
+    fn antichess(fen: &str) -> Game {
+        Game::from_fen_variant(fen, Variant::Antichess).expect("Should parse fen")
+    }
+
+    #[test]
+    fn test_captures_are_compulsory() {
+        let mut game = Game::with_variant(Variant::Antichess);
+        assert_eq!(game.legal_moves().len(), 20);
+        game.make_move("e2e3").unwrap();
+        game.make_move("b7b5").unwrap();
+
+        let moves: Vec<String> = game.legal_moves().iter().map(Move::to_string).collect();
+        assert_eq!(moves, ["f1b5"], "Taking the pawn is the only legal move.");
+        assert!(game.make_move("g1f3").is_err());
+    }
+
+    #[test]
+    fn test_king_is_an_ordinary_piece() {
+        // No check: the king may stay attacked, be captured, and be promoted to.
+        let mut game = antichess("1k6/8/8/8/8/8/8/1R5K w - - 0 1");
+        assert_eq!(game.legal_moves().len(), 1);
+        game.make_move("b1b8").unwrap();
+        assert_eq!(game.history()[0].captured, Some(Piece::King));
+
+        let game = antichess("8/1P6/8/8/8/8/6p1/8 w - - 0 1");
+        assert_eq!(game.to_san("b7b8k".parse().unwrap()).unwrap(), "b8=K");
+        assert_eq!(game.parse_san("b8=K").unwrap(), "b7b8k".parse().unwrap());
+        game.clone().make_move("b7b8k").expect("Promoting to a king is a move in Antichess.");
+    }
+
+    #[test]
+    fn test_antichess_outcomes() {
+        let mut game = antichess("1k6/8/8/8/8/8/8/1R5K w - - 0 1");
+        game.make_move("b1b8").unwrap();
+        let outcome = game.outcome().expect("Black has no pieces left");
+        assert_eq!(outcome.result, GameResult::BlackWin);
+        assert_eq!(outcome.to_string(), "Black wins by losing all pieces");
+        assert_eq!(game.result_value(), -1.0);
+
+        // White's pawn is blocked: having no move wins.
+        let game = antichess("8/8/8/8/8/p7/P7/8 w - - 0 1");
+        let outcome = game.outcome().expect("White cannot move");
+        assert_eq!(outcome.result, GameResult::WhiteWin);
+        assert_eq!(outcome.termination, Termination::Stalemate);
+        assert_eq!(game.result_value(), 1.0);
+
+        // A lone king is no draw.
+        assert!(!antichess("8/8/8/4k3/8/8/8/R3K3 b - - 0 1").is_terminal());
+    }
+
+    #[test]
+    fn test_antichess_fen_and_undo() {
+        let fen = "rnbqkbnr/pp1ppppp/8/2pP4/8/8/PPP1PPPP/RNBQKBNR w - c6 0 3";
+        let mut game = antichess(fen);
+        assert_eq!(game.to_fen(), fen);
+        assert_eq!(game.current_player(), Player::White);
+
+        game.make_move("d5c6").unwrap();
+        assert_eq!(game.history()[0].captured, Some(Piece::Pawn));
+        assert_eq!(game.to_fen(), "rnbqkbnr/pp1ppppp/2P5/8/8/8/PPP1PPPP/RNBQKBNR b - - 0 3");
+        game.undo_move();
+        assert_eq!(game.to_fen(), fen);
+
+        let kings = "k1k5/8/8/8/8/8/8/8 w - - 0 1";
+        assert_eq!(antichess(kings).to_fen(), kings);
+        assert!(Game::from_fen_variant("8/8/8 w - - 0 1", Variant::Antichess).is_err());
+        assert!(Game::from_fen_variant("P7/8/8/8/8/8/8/8 w - - 0 1", Variant::Antichess).is_err());
+        assert!(Game::with_variant(Variant::Antichess).set_chess960(true).is_err());
+    }
+
+    #[test]
+    fn test_perft_antichess() {
+        // Node counts checked against shakmaty.
+        for (fen, expected) in [
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1", vec![20, 400, 8067]),
+            ("8/1P6/8/8/8/8/6p1/8 w - - 0 1", vec![5, 25, 250, 2112]),
+            ("rnbqkbnr/pp1ppppp/8/2pP4/8/8/PPP1PPPP/RNBQKBNR w - c6 0 3", vec![1, 3, 3, 8]),
+            ("8/2k5/8/3Pp3/8/8/4K3/8 w - e6 0 1", vec![1, 8, 64, 356]),
+            ("8/8/8/8/8/8/1R6/Kk6 w - - 0 1", vec![2, 0]),
+        ] {
+            let game = antichess(fen);
+            for (depth, &nodes) in expected.iter().enumerate() {
+                assert_eq!(game.perft(depth as u32 + 1, PerftRules::MovesOnly), nodes, "{}", fen);
+            }
+        }
+    }
+}
@@ -0,0 +1,535 @@
+//! Chess960 (Fischer Random): the 960 starting positions, X-FEN/Shredder-FEN castling
+//! rights and castling written king-takes-rook, as UCI engines do with `UCI_Chess960`.
+//!
+//! chess::Board only understands castling with the king on the e-file and rooks in the
+//! corners, so in Chess960 mode the board carries no castling rights at all. The game keeps
+//! them as the files of the rooks that may still castle, and generates and plays castling
+//! moves itself.
+
+use chess::{
+    between, get_bishop_moves, get_king_moves, get_knight_moves, get_pawn_attacks, get_rook_moves,
+    BitBoard, Board, BoardBuilder, CastleRights, ChessMove, Color, File, Piece, Square, EMPTY,
+};
+use rand::Rng;
+use crate::error::Error;
+use super::Game;
+
+/// Where the two knights go among the five squares left after the bishops and queen.
+const KNIGHTS: [(usize, usize); 10] = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+
+/// The rooks each side may still castle with, by file. Indexed by colour, then king side
+/// (towards the h-file) and queen side.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct CastlingRooks([[Option<File>; 2]; 2]);
+
+impl CastlingRooks {
+    fn get(&self, color: Color, side: CastleRights) -> Option<File> {
+        self.0[color.to_index()][side_index(side)]
+    }
+
+    fn set(&mut self, color: Color, side: CastleRights, file: Option<File>) {
+        self.0[color.to_index()][side_index(side)] = file;
+    }
+
+    /// The rights left once `mv` is played: a king move gives up both, a rook leaving or
+    /// being taken on its square gives up its own.
+    fn after_move(mut self, board: &Board, mv: ChessMove) -> Self {
+        let mover = board.side_to_move();
+        if board.piece_on(mv.get_source()) == Some(Piece::King) {
+            self.0[mover.to_index()] = [None, None];
+        }
+        for color in [Color::White, Color::Black] {
+            for side in [CastleRights::KingSide, CastleRights::QueenSide] {
+                let square = self.get(color, side).map(|file| Square::make_square(color.to_my_backrank(), file));
+                if square == Some(mv.get_source()) || square == Some(mv.get_dest()) {
+                    self.set(color, side, None);
+                }
+            }
+        }
+        self
+    }
+
+    /// Whether plain chess::Board castling rights can express these: kings on the e-file
+    /// and rooks in the corners.
+    fn is_standard(&self, board: &Board) -> bool {
+        [Color::White, Color::Black].iter().all(|&color| {
+            let rooks = self.0[color.to_index()];
+            (rooks == [None, None] || board.king_square(color).get_file() == File::E)
+                && rooks[0].is_none_or(|file| file == File::H)
+                && rooks[1].is_none_or(|file| file == File::A)
+        })
+    }
+
+    fn to_castle_rights(self, color: Color) -> CastleRights {
+        match self.0[color.to_index()] {
+            [Some(_), Some(_)] => CastleRights::Both,
+            [Some(_), None] => CastleRights::KingSide,
+            [None, Some(_)] => CastleRights::QueenSide,
+            [None, None] => CastleRights::NoRights,
+        }
+    }
+
+    /// Mixed into the position hash, which cannot see rights the board does not hold.
+    fn key(&self) -> u64 {
+        let mut key = 0;
+        for (slot, file) in self.0.iter().flatten().enumerate() {
+            if let Some(file) = file {
+                key ^= super::splitmix((slot * 8 + file.to_index()) as u64);
+            }
+        }
+        key
+    }
+}
+
+impl Game {
+    /// The Chess960 starting position with the given number (0–959) in the standard
+    /// numbering; 518 is the ordinary starting position.
+    pub fn chess960(index: u16) -> Result<Game, Error> {
+        if index >= 960 {
+            return Err(Error::Chess960OutOfRange(index));
+        }
+
+        let back_rank: String = chess960_back_rank(index).iter().map(|&piece| match piece {
+            Piece::Rook => 'R',
+            Piece::Knight => 'N',
+            Piece::Bishop => 'B',
+            Piece::Queen => 'Q',
+            _ => 'K',
+        }).collect();
+        let fen = format!("{}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1", back_rank.to_lowercase(), back_rank);
+
+        let mut game = Game::from_fen(&fen)?;
+        game.set_chess960(true)?;
+        Ok(game)
+    }
+
+    /// A Chess960 starting position picked at random, for self-play on varied openings.
+    pub fn random_chess960() -> Game {
+        let index = rand::thread_rng().gen_range(0..960);
+        Game::chess960(index).expect("index is in range")
+    }
+
+    /// Whether castling follows Chess960 rules and is written king-takes-rook (`e1h1`), as
+    /// with the UCI option `UCI_Chess960`.
+    pub fn is_chess960(&self) -> bool {
+        self.chess960.is_some()
+    }
+
+    /// Switches Chess960 castling on or off, like the UCI option `UCI_Chess960`. Only
+    /// possible before the first move, and switching off fails when the castling rights
+    /// need Chess960 rules.
+    pub fn set_chess960(&mut self, enabled: bool) -> Result<(), Error> {
+        if enabled == self.is_chess960() {
+            return Ok(());
+        }
+        if !self.history.is_empty() {
+            return Err(Error::Chess960Unavailable("only possible before the first move".to_string()));
+        }
+        if self.antichess.is_some() {
+            return Err(Error::Chess960Unavailable("Antichess has no castling".to_string()));
+        }
+
+        let mut builder = BoardBuilder::from(&self.board);
+        if enabled {
+            let mut rooks = CastlingRooks::default();
+            for color in [Color::White, Color::Black] {
+                let rights = self.board.castle_rights(color);
+                if rights.has_kingside() {
+                    rooks.set(color, CastleRights::KingSide, Some(File::H));
+                }
+                if rights.has_queenside() {
+                    rooks.set(color, CastleRights::QueenSide, Some(File::A));
+                }
+            }
+            builder.castle_rights(Color::White, CastleRights::NoRights);
+            builder.castle_rights(Color::Black, CastleRights::NoRights);
+            self.chess960 = Some(rooks);
+        } else {
+            let rooks = self.chess960.unwrap_or_default();
+            if !rooks.is_standard(&self.board) {
+                return Err(Error::Chess960Unavailable("these castling rights need Chess960 rules".to_string()));
+            }
+            builder.castle_rights(Color::White, rooks.to_castle_rights(Color::White));
+            builder.castle_rights(Color::Black, rooks.to_castle_rights(Color::Black));
+            self.chess960 = None;
+        }
+        self.board = Board::try_from(builder).map_err(|e| Error::Chess960Unavailable(e.to_string()))?;
+
+        self.positions.clear();
+        self.increment_position_count();
+        Ok(())
+    }
+
+    /// Hash contribution of castling rights the board does not know about.
+    pub(super) fn castling_key(&self) -> u64 {
+        self.chess960.map_or(0, |rooks| rooks.key())
+    }
+
+    /// `color`'s castling rights, whichever way the game keeps them.
+    pub(super) fn castle_rights(&self, color: Color) -> CastleRights {
+        match self.chess960 {
+            _ if self.antichess.is_some() => CastleRights::NoRights,
+            Some(rooks) => rooks.to_castle_rights(color),
+            None => self.board.castle_rights(color),
+        }
+    }
+
+    /// Chess960 castling rights after `mv`, which has not been played yet.
+    pub(super) fn chess960_after(&self, mv: ChessMove) -> Option<CastlingRooks> {
+        self.chess960.map(|rooks| rooks.after_move(&self.board, mv))
+    }
+
+    /// Castling moves for the side to move in Chess960 mode, written king-takes-rook.
+    /// Standard castling comes from chess::MoveGen and is not included.
+    pub(super) fn castling_moves(&self) -> Vec<ChessMove> {
+        let Some(rooks) = self.chess960 else {
+            return Vec::new();
+        };
+        let color = self.board.side_to_move();
+        if *self.board.checkers() != EMPTY {
+            return Vec::new();
+        }
+
+        let king = self.board.king_square(color);
+        let rank = color.to_my_backrank();
+        [CastleRights::KingSide, CastleRights::QueenSide].into_iter().filter_map(|side| {
+            let rook = Square::make_square(rank, rooks.get(color, side)?);
+            let (king_to, rook_to) = castled_squares(color, side);
+
+            let occupied = *self.board.combined() ^ BitBoard::from_square(king) ^ BitBoard::from_square(rook);
+            let king_path = span(king, king_to);
+            if (king_path | span(rook, rook_to)) & occupied != EMPTY {
+                return None;
+            }
+            if king_path.into_iter().any(|sq| is_attacked(&self.board, sq, !color, occupied)) {
+                return None;
+            }
+            Some(ChessMove::new(king, rook, None))
+        }).collect()
+    }
+
+    /// Which way `mv` castles, if it is a castling move: `e1g1` in standard chess, the king
+    /// taking its own rook in Chess960.
+    pub(super) fn castling_side(&self, mv: ChessMove) -> Option<CastleRights> {
+        let source = mv.get_source();
+        let dest = mv.get_dest();
+        if self.antichess.is_some() || self.board.piece_on(source) != Some(Piece::King) {
+            return None;
+        }
+
+        if self.is_chess960() {
+            let color = self.board.side_to_move();
+            if self.board.piece_on(dest) != Some(Piece::Rook) || self.board.color_on(dest) != Some(color) {
+                return None;
+            }
+            Some(if dest.get_file() > source.get_file() { CastleRights::KingSide } else { CastleRights::QueenSide })
+        } else if source.get_file() == File::E && source.get_rank() == dest.get_rank() {
+            match dest.get_file() {
+                File::G => Some(CastleRights::KingSide),
+                File::C => Some(CastleRights::QueenSide),
+                _ => None,
+            }
+        } else {
+            None
+        }
+    }
+
+    /// The castling move to one side for the side to move, if it is legal.
+    pub(super) fn castling_move(&self, side: CastleRights) -> Option<ChessMove> {
+        if self.is_chess960() {
+            self.castling_moves().into_iter().find(|&mv| self.castling_side(mv) == Some(side))
+        } else {
+            let king = self.board.king_square(self.board.side_to_move());
+            let file = if side == CastleRights::KingSide { File::G } else { File::C };
+            let mv = ChessMove::new(king, Square::make_square(king.get_rank(), file), None);
+            (king.get_file() == File::E && self.board.legal(mv)).then_some(mv)
+        }
+    }
+
+    /// The board after a Chess960 castling move from `castling_moves`.
+    pub(super) fn castled_board(&self, mv: ChessMove) -> Board {
+        let color = self.board.side_to_move();
+        let side = self.castling_side(mv).expect("a castling move");
+        let (king_to, rook_to) = castled_squares(color, side);
+
+        let mut builder = BoardBuilder::from(&self.board);
+        builder
+            .clear_square(mv.get_source())
+            .clear_square(mv.get_dest())
+            .piece(king_to, Piece::King, color)
+            .piece(rook_to, Piece::Rook, color)
+            .side_to_move(!color)
+            .en_passant(None);
+        Board::try_from(builder).expect("castling leaves a valid board")
+    }
+
+    /// The FEN castling field. Chess960 rights are written X-FEN style: `K`/`Q` for the
+    /// outermost rook on a side, the rook's file otherwise.
+    pub(super) fn castling_fen(&self, board_field: &str) -> String {
+        let Some(rooks) = self.chess960 else {
+            return board_field.to_string();
+        };
+
+        let mut field = String::new();
+        for color in [Color::White, Color::Black] {
+            for side in [CastleRights::KingSide, CastleRights::QueenSide] {
+                let Some(file) = rooks.get(color, side) else {
+                    continue;
+                };
+                let outermost = rook_files(&self.board, color).into_iter().all(|other| match side {
+                    CastleRights::KingSide => other <= file,
+                    _ => other >= file,
+                });
+                let c = match (outermost, side) {
+                    (true, CastleRights::KingSide) => 'K',
+                    (true, _) => 'Q',
+                    (false, _) => (b'A' + file.to_index() as u8) as char,
+                };
+                field.push(if color == Color::White { c } else { c.to_ascii_lowercase() });
+            }
+        }
+        if field.is_empty() {
+            field.push('-');
+        }
+        field
+    }
+}
+
+/// Reads a FEN castling field in standard, X-FEN or Shredder-FEN form against a board that
+/// has no castling rights. Returns `None` when plain chess::Board rights can express it.
+pub(super) fn parse_castling(board: &Board, field: &str) -> Result<Option<CastlingRooks>, String> {
+    let bad_field = || format!("bad castling rights '{}'", field);
+
+    let mut rooks = CastlingRooks::default();
+    let mut file_letters = false;
+    for c in field.chars().filter(|&c| c != '-') {
+        let color = if c.is_ascii_uppercase() { Color::White } else { Color::Black };
+        let king = board.king_square(color);
+        if king.get_rank() != color.to_my_backrank() {
+            return Err(bad_field());
+        }
+        let files = rook_files(board, color);
+
+        let file = match c.to_ascii_lowercase() {
+            'k' => files.into_iter().filter(|&f| f > king.get_file()).max_by_key(File::to_index),
+            'q' => files.into_iter().filter(|&f| f < king.get_file()).min_by_key(File::to_index),
+            'a'..='h' => {
+                file_letters = true;
+                let file = File::from_index(c.to_ascii_lowercase() as usize - 'a' as usize);
+                files.contains(&file).then_some(file)
+            }
+            _ => None,
+        }.ok_or_else(bad_field)?;
+
+        let side = if file > king.get_file() { CastleRights::KingSide } else { CastleRights::QueenSide };
+        rooks.set(color, side, Some(file));
+    }
+
+    if !file_letters && rooks.is_standard(board) {
+        Ok(None)
+    } else {
+        Ok(Some(rooks))
+    }
+}
+
+/// Back rank of Chess960 position `index`, from the a-file to the h-file.
+fn chess960_back_rank(index: u16) -> [Piece; 8] {
+    let mut rank: [Option<Piece>; 8] = [None; 8];
+    let mut n = index as usize;
+
+    rank[(n % 4) * 2 + 1] = Some(Piece::Bishop);
+    n /= 4;
+    rank[(n % 4) * 2] = Some(Piece::Bishop);
+    n /= 4;
+
+    let empty: Vec<usize> = (0..8).filter(|&i| rank[i].is_none()).collect();
+    rank[empty[n % 6]] = Some(Piece::Queen);
+    n /= 6;
+
+    let empty: Vec<usize> = (0..8).filter(|&i| rank[i].is_none()).collect();
+    let (first, second) = KNIGHTS[n];
+    rank[empty[first]] = Some(Piece::Knight);
+    rank[empty[second]] = Some(Piece::Knight);
+
+    // The king goes between the rooks on the last three squares.
+    let empty: Vec<usize> = (0..8).filter(|&i| rank[i].is_none()).collect();
+    for (&i, piece) in empty.iter().zip([Piece::Rook, Piece::King, Piece::Rook]) {
+        rank[i] = Some(piece);
+    }
+
+    rank.map(|piece| piece.expect("every square is filled"))
+}
+
+/// Where king and rook end up: the g- and f-files on the king side, c- and d-files on the
+/// queen side, whatever the starting position.
+fn castled_squares(color: Color, side: CastleRights) -> (Square, Square) {
+    let rank = color.to_my_backrank();
+    match side {
+        CastleRights::KingSide => (Square::make_square(rank, File::G), Square::make_square(rank, File::F)),
+        _ => (Square::make_square(rank, File::C), Square::make_square(rank, File::D)),
+    }
+}
+
+fn rook_files(board: &Board, color: Color) -> Vec<File> {
+    let rank = color.to_my_backrank();
+    (board.pieces(Piece::Rook) & board.color_combined(color))
+        .filter(|sq| sq.get_rank() == rank)
+        .map(|sq| sq.get_file())
+        .collect()
+}
+
+/// Both squares and everything between them.
+fn span(from: Square, to: Square) -> BitBoard {
+    between(from, to) | BitBoard::from_square(from) | BitBoard::from_square(to)
+}
+
+/// Whether `by` attacks `sq`, with sliding attacks blocked by `occupied`.
+pub(super) fn is_attacked(board: &Board, sq: Square, by: Color, occupied: BitBoard) -> bool {
+    is_attacked_by(|piece| board.pieces(piece) & board.color_combined(by), sq, by, occupied)
+}
+
+/// `is_attacked` for pieces that need not be on a `Board`: `pieces` gives `by`'s pieces of
+/// each kind.
+pub(super) fn is_attacked_by(pieces: impl Fn(Piece) -> BitBoard, sq: Square, by: Color, occupied: BitBoard) -> bool {
+    let queens = pieces(Piece::Queen);
+    let rooks = pieces(Piece::Rook) | queens;
+    let bishops = pieces(Piece::Bishop) | queens;
+
+    get_rook_moves(sq, occupied) & rooks != EMPTY
+        || get_bishop_moves(sq, occupied) & bishops != EMPTY
+        || get_knight_moves(sq) & pieces(Piece::Knight) != EMPTY
+        || get_king_moves(sq) & pieces(Piece::King) != EMPTY
+        || get_pawn_attacks(sq, !by, pieces(Piece::Pawn)) != EMPTY
+}
+
+fn side_index(side: CastleRights) -> usize {
+    if side == CastleRights::KingSide { 0 } else { 1 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Move, PerftRules};
+
+    fn mv(uci: &str) -> Move {
+        uci.parse().expect("Should be valid UCI")
+    }
+
+    #[test]
+    fn test_chess960_starting_positions() {
+        let standard = Game::chess960(518).unwrap();
+        assert!(standard.is_chess960());
+        assert_eq!(standard.to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+
+        assert_eq!(Game::chess960(0).unwrap().to_fen(), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1");
+        assert_eq!(Game::chess960(959).unwrap().to_fen(), "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w KQkq - 0 1");
+        assert!(matches!(Game::chess960(960), Err(Error::Chess960OutOfRange(960))));
+
+        let mut placements: Vec<String> = (0..960)
+            .map(|i| chess960_back_rank(i).iter().map(|p| format!("{:?}", p)).collect())
+            .collect();
+        placements.sort();
+        placements.dedup();
+        assert_eq!(placements.len(), 960, "Every index gives a different position.");
+    }
+
+    #[test]
+    fn test_chess960_castling_is_king_takes_rook() {
+        let mut game = Game::from_fen("bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/B4RKR w KQkq - 0 1").unwrap();
+        assert!(game.is_chess960());
+
+        // The queen-side rook is on f1; the king-side castle is blocked by it.
+        assert!(game.legal_moves().contains(&mv("g1f1")));
+        assert!(!game.legal_moves().contains(&mv("g1h1")));
+        assert!(game.make_move("g1c1").is_err(), "Castling is not written as a king move in Chess960.");
+        assert_eq!(game.to_san(mv("g1f1")).unwrap(), "O-O-O");
+        assert_eq!(game.parse_san("O-O-O").unwrap(), mv("g1f1"));
+        assert!(game.parse_san("O-O").is_err());
+
+        game.make_move("g1f1").unwrap();
+        assert_eq!(game.to_fen(), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/B1KR3R b kq - 1 1");
+        assert_eq!(game.history()[0].captured, None);
+
+        game.undo_move();
+        assert_eq!(game.to_fen(), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/B4RKR w KQkq - 0 1");
+    }
+
+    #[test]
+    fn test_chess960_castling_through_attacks() {
+        // After O-O-O the b1 rook would have left the king on c1 exposed to the queen on a1.
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/qRK3R1 w GB - 0 1").unwrap();
+        assert!(game.legal_moves().contains(&mv("c1g1")));
+        assert!(!game.legal_moves().contains(&mv("c1b1")));
+
+        // No castling out of check.
+        let game = Game::from_fen("4k3/8/8/8/8/8/4r3/1R2K1R1 w GB - 0 1").unwrap();
+        assert!(!game.legal_moves().contains(&mv("e1g1")));
+        assert!(!game.legal_moves().contains(&mv("e1b1")));
+        let game = Game::from_fen("4k3/8/8/8/2r5/8/8/1R2K1R1 w GB - 0 1").unwrap();
+        assert!(game.legal_moves().contains(&mv("e1g1")));
+        assert!(!game.legal_moves().contains(&mv("e1b1")), "The king would pass the attacked c1.");
+    }
+
+    #[test]
+    fn test_chess960_fen_formats() {
+        let shredder = Game::from_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9").unwrap();
+        let x_fen = Game::from_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9").unwrap();
+        assert!(shredder.is_chess960());
+        assert_eq!(shredder.to_fen(), "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9");
+        assert_eq!(shredder.to_fen(), x_fen.to_fen());
+        assert_eq!(shredder.get_hash(), x_fen.get_hash());
+
+        // An inner rook needs its file letter in X-FEN.
+        let inner = Game::from_fen("4k3/8/8/8/8/8/8/RR2K3 w B - 0 1").unwrap();
+        assert_eq!(inner.to_fen(), "4k3/8/8/8/8/8/8/RR2K3 w B - 0 1");
+
+        assert!(Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w H - 0 1").is_err(), "There is no rook on h1.");
+        assert!(!Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap().is_chess960());
+    }
+
+    #[test]
+    fn test_chess960_castling_rights_follow_moves() {
+        let mut game = Game::chess960(0).unwrap();
+        for uci in ["h2h4", "h7h5", "h1h3"] {
+            game.make_move(uci).unwrap();
+        }
+        assert!(game.to_fen().contains(" Qkq "), "The h1 rook gave up its right by moving.");
+        game.make_move("h8h6").unwrap();
+        game.make_move("g1h1").unwrap();
+        assert!(game.to_fen().contains(" q "), "A king move gives up both rights.");
+    }
+
+    #[test]
+    fn test_set_chess960() {
+        let mut game = Game::new();
+        game.set_chess960(true).unwrap();
+        assert!(game.legal_moves().len() == 20);
+        assert_eq!(game.to_fen(), Game::new().to_fen());
+
+        game.set_chess960(false).unwrap();
+        assert!(!game.is_chess960());
+
+        let mut game = Game::chess960(0).unwrap();
+        assert!(matches!(game.set_chess960(false), Err(Error::Chess960Unavailable(_))), "BBQNNRKR castling needs Chess960 rules.");
+        game.make_move("g2g3").unwrap();
+        assert!(game.set_chess960(true).is_ok());
+        assert!(Game::new().make_move("e2e4").unwrap().set_chess960(true).is_err());
+    }
+
+    #[test]
+    fn test_chess960_perft() {
+        for (fen, expected) in [
+            ("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", [21, 528, 12189, 326672]),
+            ("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9", [21, 807, 18002, 667366]),
+            ("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9", [20, 479, 10471, 273318]),
+            ("qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9", [22, 593, 13440, 382958]),
+            ("1nbbnrkr/p1p1ppp1/3p4/1p3P1p/3Pq2P/8/PPP1P1P1/QNBBNRKR w HFhf - 0 9", [28, 1120, 31058, 1171749]),
+            ("1r2k1r1/8/8/8/8/8/8/1R2K1R1 w GBgb - 0 1", [25, 525, 12297, 272627]),
+        ] {
+            let game = Game::from_fen(fen).unwrap();
+            for (depth, &nodes) in expected.iter().enumerate() {
+                assert_eq!(game.perft(depth as u32 + 1, PerftRules::MovesOnly), nodes, "perft({}) of {}", depth + 1, fen);
+            }
+        }
+    }
+}
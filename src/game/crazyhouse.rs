@@ -0,0 +1,351 @@
+//! Crazyhouse: a captured piece changes sides and goes into the capturer's pocket, from where
+//! it can later be dropped onto any empty square instead of making a move.
+
+use chess::{between, BitBoard, Board, BoardBuilder, Color, File, Piece, Rank, Square, EMPTY};
+use super::{Game, Move, Player, Variant};
+
+/// The pieces a pocket can hold, in FEN order.
+const POCKET_PIECES: [Piece; 5] = [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen];
+
+/// Extra inputs `Game::encode` appends in Crazyhouse: the pocket counts for White, then Black.
+pub(super) const POCKET_FEATURES: usize = 10;
+
+/// Pieces in hand, together with the promoted pieces on the board, which go back into a
+/// pocket as pawns when they are captured.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Pockets {
+    /// Counts by colour, then by piece index (pawn to queen).
+    counts: [[u8; 5]; 2],
+    promoted: BitBoard,
+}
+
+impl Game {
+    /// How many of `piece` `player` holds in hand, ready to drop. Always 0 outside Crazyhouse.
+    pub fn pocket(&self, player: Player, piece: Piece) -> u32 {
+        match piece {
+            Piece::King => 0,
+            _ => self.pockets.counts[Color::from(player).to_index()][piece.to_index()] as u32,
+        }
+    }
+
+    /// The legal drops for the side to move.
+    pub(super) fn drop_moves(&self) -> Vec<Move> {
+        if self.variant != Variant::Crazyhouse {
+            return Vec::new();
+        }
+        let counts = self.pockets.counts[self.board.side_to_move().to_index()];
+        if counts == [0; 5] {
+            return Vec::new();
+        }
+
+        let targets = self.drop_targets();
+        let mut moves = Vec::new();
+        for piece in POCKET_PIECES.into_iter().filter(|piece| counts[piece.to_index()] > 0) {
+            moves.extend(targets
+                .filter(|&to| piece != Piece::Pawn || !is_back_rank(to))
+                .map(|to| Move::new_drop(piece, to)));
+        }
+        moves
+    }
+
+    pub(super) fn is_legal_drop(&self, piece: Piece, to: Square) -> bool {
+        self.variant == Variant::Crazyhouse
+            && self.pocket(self.current_player(), piece) > 0
+            && self.drop_targets() & BitBoard::from_square(to) != EMPTY
+            && (piece != Piece::Pawn || !is_back_rank(to))
+    }
+
+    /// Empty squares a piece may be dropped on: any of them, unless the king is in check,
+    /// when a drop has to block the one checking line piece.
+    fn drop_targets(&self) -> BitBoard {
+        let empty = !*self.board.combined();
+        let checkers = *self.board.checkers();
+        match checkers.popcnt() {
+            0 => empty,
+            1 => {
+                let king = self.board.king_square(self.board.side_to_move());
+                between(king, checkers.to_square()) & empty
+            }
+            _ => EMPTY,
+        }
+    }
+
+    /// The board after dropping `piece` on `to`.
+    pub(super) fn dropped_board(&self, piece: Piece, to: Square) -> Board {
+        let color = self.board.side_to_move();
+        let mut builder = BoardBuilder::from(&self.board);
+        builder.piece(to, piece, color).side_to_move(!color).en_passant(None);
+        Board::try_from(builder).expect("a legal drop leaves a valid board")
+    }
+
+    /// The pockets once `mv`, taking `captured`, is played.
+    pub(super) fn pockets_after(&self, mv: Move, captured: Option<Piece>) -> Pockets {
+        let mut pockets = self.pockets;
+        if self.variant != Variant::Crazyhouse {
+            return pockets;
+        }
+        let us = self.board.side_to_move().to_index();
+        if let Some(piece) = mv.drop {
+            pockets.counts[us][piece.to_index()] -= 1;
+            return pockets;
+        }
+
+        let from = BitBoard::from_square(mv.source());
+        let to = BitBoard::from_square(mv.to());
+        if let Some(piece) = captured {
+            let piece = if pockets.promoted & to != EMPTY { Piece::Pawn } else { piece };
+            pockets.counts[us][piece.to_index()] += 1;
+        }
+        let was_promoted = pockets.promoted & from != EMPTY;
+        pockets.promoted &= !(from | to);
+        if was_promoted || mv.promotion().is_some() {
+            pockets.promoted |= to;
+        }
+        pockets
+    }
+
+    /// Hash contribution of the pockets, which the board does not know about.
+    pub(super) fn pockets_key(&self) -> u64 {
+        if self.variant != Variant::Crazyhouse {
+            return 0;
+        }
+        let mut key = super::splitmix(0x2C00 ^ self.pockets.promoted.0);
+        for (slot, &count) in self.pockets.counts.iter().flatten().enumerate() {
+            key ^= super::splitmix(0x2D00 + (slot as u64) * 32 + count as u64);
+        }
+        key
+    }
+
+    /// Pocket counts as encoder inputs, `first`'s then the other side's.
+    pub(super) fn pocket_features(&self, first: Color) -> Vec<f32> {
+        [first, !first].iter()
+            .flat_map(|color| self.pockets.counts[color.to_index()])
+            .map(|count| count as f32 / 8.0)
+            .collect()
+    }
+
+    /// The FEN placement field with promoted pieces marked `~` and the pockets appended in
+    /// brackets, or `None` outside Crazyhouse.
+    pub(super) fn crazyhouse_placement(&self) -> Option<String> {
+        if self.variant != Variant::Crazyhouse {
+            return None;
+        }
+
+        let mut placement = String::new();
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                let sq = Square::make_square(Rank::from_index(rank), File::from_index(file));
+                match (self.board.piece_on(sq), self.board.color_on(sq)) {
+                    (Some(piece), Some(color)) => {
+                        if empty > 0 {
+                            placement.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        placement.push_str(&piece.to_string(color));
+                        if self.pockets.promoted & BitBoard::from_square(sq) != EMPTY {
+                            placement.push('~');
+                        }
+                    }
+                    _ => empty += 1,
+                }
+            }
+            if empty > 0 {
+                placement.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                placement.push('/');
+            }
+        }
+
+        placement.push('[');
+        for color in [Color::White, Color::Black] {
+            for piece in POCKET_PIECES {
+                for _ in 0..self.pockets.counts[color.to_index()][piece.to_index()] {
+                    placement.push_str(&piece.to_string(color));
+                }
+            }
+        }
+        placement.push(']');
+        Some(placement)
+    }
+}
+
+/// Splits a Crazyhouse FEN placement into a plain placement chess::Board can read and the
+/// pockets. Pockets may be given in brackets (`.../RNBQKBNR[Pn]`) or as a ninth rank
+/// (`.../RNBQKBNR/Pn`), and may be left out.
+pub(super) fn split_placement(field: &str) -> Result<(String, Pockets), String> {
+    let bad_field = || format!("bad Crazyhouse placement '{}'", field);
+
+    let (board_part, pocket_part) = if let Some(start) = field.find('[') {
+        let pocket = field[start + 1..].strip_suffix(']').ok_or_else(bad_field)?;
+        (&field[..start], pocket)
+    } else if field.matches('/').count() == 8 {
+        field.rsplit_once('/').expect("the field has slashes")
+    } else {
+        (field, "")
+    };
+
+    let mut pockets = Pockets::default();
+    for c in pocket_part.chars() {
+        let color = if c.is_ascii_uppercase() { Color::White } else { Color::Black };
+        let piece = POCKET_PIECES.into_iter()
+            .find(|piece| piece.to_string(color) == c.to_string())
+            .ok_or_else(bad_field)?;
+        pockets.counts[color.to_index()][piece.to_index()] += 1;
+    }
+
+    let mut placement = String::new();
+    let (mut rank, mut file) = (7usize, 0usize);
+    for c in board_part.chars() {
+        match c {
+            '~' => {
+                if file == 0 {
+                    return Err(bad_field());
+                }
+                let sq = Square::make_square(Rank::from_index(rank), File::from_index(file - 1));
+                pockets.promoted |= BitBoard::from_square(sq);
+                continue;
+            }
+            '/' => {
+                rank = rank.checked_sub(1).ok_or_else(bad_field)?;
+                file = 0;
+            }
+            '1'..='8' => file += c as usize - '0' as usize,
+            _ => file += 1,
+        }
+        placement.push(c);
+    }
+
+    Ok((placement, pockets))
+}
+
+fn is_back_rank(sq: Square) -> bool {
+    matches!(sq.get_rank(), Rank::First | Rank::Eighth)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::PerftRules;
+
+    fn mv(uci: &str) -> Move {
+        uci.parse().expect("Should be valid UCI")
+    }
+
+    fn crazyhouse(fen: &str) -> Game {
+        Game::from_fen_variant(fen, Variant::Crazyhouse).expect("Should parse fen")
+    }
+
+    #[test]
+    fn test_captures_fill_the_pocket() {
+        let mut game = Game::with_variant(Variant::Crazyhouse);
+        assert_eq!(game.to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[] w KQkq - 0 1");
+
+        for uci in ["e2e4", "d7d5", "e4d5", "d8d5"] {
+            game.make_move(uci).unwrap();
+        }
+        assert_eq!(game.pocket(Player::White, Piece::Pawn), 1);
+        assert_eq!(game.pocket(Player::Black, Piece::Pawn), 1);
+        assert_eq!(game.to_fen(), "rnb1kbnr/ppp1pppp/8/3q4/8/8/PPPP1PPP/RNBQKBNR[Pp] w KQkq - 0 3");
+
+        game.play_move(mv("P@e6")).unwrap();
+        assert_eq!(game.pocket(Player::White, Piece::Pawn), 0);
+        assert_eq!(game.halfmove_clock(), 0, "A pawn drop resets the clock.");
+        assert_eq!(game.history().last().unwrap().mv.dropped_piece(), Some(Piece::Pawn));
+
+        game.undo_move();
+        assert_eq!(game.pocket(Player::White, Piece::Pawn), 1);
+        assert_eq!(game.to_fen(), "rnb1kbnr/ppp1pppp/8/3q4/8/8/PPPP1PPP/RNBQKBNR[Pp] w KQkq - 0 3");
+    }
+
+    #[test]
+    fn test_promoted_pieces_return_as_pawns() {
+        let mut game = crazyhouse("r3k3/1P6/8/8/8/8/8/4K3[] w - - 0 1");
+        game.make_move("b7b8q").unwrap();
+        assert_eq!(game.to_fen(), "rQ~2k3/8/8/8/8/8/8/4K3[] b - - 0 1");
+
+        game.make_move("a8b8").unwrap();
+        assert_eq!(game.pocket(Player::Black, Piece::Pawn), 1);
+        assert_eq!(game.pocket(Player::Black, Piece::Queen), 0);
+        assert_eq!(game.to_fen(), "1r2k3/8/8/8/8/8/8/4K3[p] w - - 0 2");
+    }
+
+    #[test]
+    fn test_drop_legality() {
+        let mut game = crazyhouse("4k3/8/8/8/8/8/8/4K3[P] w - - 0 1");
+        assert_eq!(game.legal_moves().len(), 5 + 48, "King moves, then pawn drops off the back ranks.");
+        assert!(game.play_move(mv("P@e1")).is_err(), "Drops go on empty squares only.");
+        assert!(game.play_move(mv("P@a8")).is_err(), "Pawns cannot be dropped on the back rank.");
+        assert!(game.play_move(mv("N@c3")).is_err(), "Only pieces in hand can be dropped.");
+
+        // In check from a rook, a drop has to block.
+        let game = crazyhouse("4k3/8/8/8/8/8/8/r3K3[N] w - - 0 1");
+        let drops: Vec<String> = game.legal_moves().iter()
+            .filter(|m| m.dropped_piece().is_some())
+            .map(Move::to_string)
+            .collect();
+        assert_eq!(drops, ["N@b1", "N@c1", "N@d1"]);
+
+        // A knight check cannot be blocked.
+        let game = crazyhouse("4k3/8/8/8/8/3n4/8/4K3[Q] w - - 0 1");
+        assert!(game.legal_moves().iter().all(|m| m.dropped_piece().is_none()));
+    }
+
+    #[test]
+    fn test_crazyhouse_fen() {
+        let fen = "r1bqk2r/pppp1pp1/2n2n2/2b1p3/2B1P3/5N2/PPPP1PP1/RNBQK2R[Pp] w KQkq - 0 5";
+        let game = crazyhouse(fen);
+        assert_eq!(game.to_fen(), fen);
+
+        let ninth_rank = crazyhouse("r1bqk2r/pppp1pp1/2n2n2/2b1p3/2B1P3/5N2/PPPP1PP1/RNBQK2R/Pp w KQkq - 0 5");
+        assert_eq!(ninth_rank.to_fen(), fen);
+
+        let empty = crazyhouse("r1bqk2r/pppp1pp1/2n2n2/2b1p3/2B1P3/5N2/PPPP1PP1/RNBQK2R[] w KQkq - 0 5");
+        assert_ne!(game.get_hash(), empty.get_hash(), "Pockets are part of the position.");
+
+        assert!(Game::from_fen_variant("4k3/8/8/8/8/8/8/4K3[K] w - - 0 1", Variant::Crazyhouse).is_err());
+        assert!(Game::from_fen_variant("4k3/8/8/8/8/8/8/4K3[P w - - 0 1", Variant::Crazyhouse).is_err());
+    }
+
+    #[test]
+    fn test_drop_notation() {
+        let drop = mv("N@f3");
+        assert_eq!(drop.dropped_piece(), Some(Piece::Knight));
+        assert_eq!(drop.to(), Square::F3);
+        assert_eq!(drop.to_string(), "N@f3");
+        assert_eq!(mv("n@f3"), drop);
+        assert!("K@f3".parse::<Move>().is_err());
+
+        let game = crazyhouse("rnb1kbnr/ppp1pppp/8/3q4/8/8/PPPP1PPP/RNBQKBNR[Pp] w KQkq - 0 3");
+        assert_eq!(game.to_san(mv("P@e6")).unwrap(), "@e6");
+        assert_eq!(game.parse_san("@e6").unwrap(), mv("P@e6"));
+        assert_eq!(game.parse_san("P@e6").unwrap(), mv("P@e6"));
+        assert!(game.parse_san("N@e6").is_err());
+
+        let game = crazyhouse("k7/8/1K6/8/8/8/8/8[Q] w - - 0 1");
+        assert_eq!(game.to_san(mv("Q@a7")).unwrap(), "Q@a7#");
+        assert_eq!(game.to_san(mv("Q@c7")).unwrap(), "Q@c7");
+
+        // Mate on the board, but Black can block with a knight from the pocket.
+        let game = crazyhouse("6k1/5ppp/8/8/8/8/8/R5K1[n] w - - 0 1");
+        assert_eq!(game.to_san(mv("a1a8")).unwrap(), "Ra8+");
+    }
+
+    #[test]
+    fn test_perft_crazyhouse() {
+        // Node counts checked against shakmaty.
+        for (fen, expected) in [
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[] w KQkq - 0 1", vec![20, 400, 8902]),
+            ("r1bqk2r/pppp1pp1/2n2n2/2b1p3/2B1P3/5N2/PPPP1PP1/RNBQK2R[Pp] w KQkq - 0 5", vec![68, 4594]),
+            ("4k3/1P6/8/8/8/8/8/4K3[NPq] b - - 0 1", vec![66, 5360]),
+            ("2q1k3/8/8/8/8/8/8/R3K2R~[Bp] w KQ - 0 1", vec![84, 5230]),
+            ("4k3/8/8/8/8/8/8/r3K3[Nn] w - - 0 1", vec![6, 462, 15235]),
+        ] {
+            let game = crazyhouse(fen);
+            for (depth, &nodes) in expected.iter().enumerate() {
+                assert_eq!(game.perft(depth as u32 + 1, PerftRules::MovesOnly), nodes, "{}", fen);
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Text diagrams of a position, for tests, logs and terminal output.
+
+use std::fmt;
+use chess::{Color, File, Piece, Rank, Square};
+use super::Game;
+
+/// How `Game::diagram` draws the board. The default is what `Display` prints: ASCII letters
+/// with coordinates, the side to move and the last move highlighted, White at the bottom.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiagramOptions {
+    /// Chess symbols (♔, ♟, ...) instead of FEN letters.
+    pub unicode: bool,
+    /// Rank numbers down the side and file letters along the bottom.
+    pub coordinates: bool,
+    /// A line under the board saying who is to move, or how the game ended.
+    pub side_to_move: bool,
+    /// Brackets around the squares the last move came from and went to.
+    pub last_move: bool,
+    /// Black at the bottom.
+    pub flipped: bool,
+}
+
+impl Default for DiagramOptions {
+    fn default() -> Self {
+        DiagramOptions { unicode: false, coordinates: true, side_to_move: true, last_move: true, flipped: false }
+    }
+}
+
+impl Game {
+    /// Draws the position as text, one line per rank.
+    pub fn diagram(&self, options: DiagramOptions) -> String {
+        let highlighted: Vec<Square> = match (options.last_move, self.history.last()) {
+            (true, Some(record)) if record.mv.dropped_piece().is_some() => vec![record.mv.to()],
+            (true, Some(record)) => vec![record.mv.source(), record.mv.to()],
+            _ => Vec::new(),
+        };
+        let mut ranks: Vec<usize> = (0..8).rev().collect();
+        let mut files: Vec<usize> = (0..8).collect();
+        if options.flipped {
+            ranks.reverse();
+            files.reverse();
+        }
+
+        let mut out = String::new();
+        for &rank in &ranks {
+            if options.coordinates {
+                out.push_str(&format!("{} ", rank + 1));
+            }
+            for &file in &files {
+                let sq = Square::make_square(Rank::from_index(rank), File::from_index(file));
+                let symbol = match (self.piece_on(sq), self.color_on(sq)) {
+                    (Some(piece), Some(color)) => piece_symbol(piece, color, options.unicode),
+                    _ if options.unicode => '·',
+                    _ => '.',
+                };
+                if highlighted.contains(&sq) {
+                    out.push_str(&format!("[{}]", symbol));
+                } else {
+                    out.push_str(&format!(" {} ", symbol));
+                }
+            }
+            out.truncate(out.trim_end().len());
+            out.push('\n');
+        }
+
+        if options.coordinates {
+            out.push_str("  ");
+            for &file in &files {
+                out.push_str(&format!(" {} ", (b'a' + file as u8) as char));
+            }
+            out.truncate(out.trim_end().len());
+            out.push('\n');
+        }
+        if options.side_to_move {
+            match self.outcome() {
+                Some(outcome) => out.push_str(&outcome.to_string()),
+                None => out.push_str(&format!("{} to move", self.current_player())),
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// The default diagram; the alternate form (`{:#}`) uses Unicode pieces.
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let options = DiagramOptions { unicode: f.alternate(), ..DiagramOptions::default() };
+        write!(f, "{}", self.diagram(options))
+    }
+}
+
+fn piece_symbol(piece: Piece, color: Color, unicode: bool) -> char {
+    if !unicode {
+        return piece.to_string(color).chars().next().expect("pieces have a letter");
+    }
+    let symbols = match color {
+        Color::White => ['♙', '♘', '♗', '♖', '♕', '♔'],
+        Color::Black => ['♟', '♞', '♝', '♜', '♛', '♚'],
+    };
+    symbols[piece.to_index()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ascii_diagram() {
+        let mut game = Game::new();
+        game.make_move("e2e4").unwrap();
+        assert_eq!(game.to_string(), "\
+8  r  n  b  q  k  b  n  r
+7  p  p  p  p  p  p  p  p
+6  .  .  .  .  .  .  .  .
+5  .  .  .  .  .  .  .  .
+4  .  .  .  . [P] .  .  .
+3  .  .  .  .  .  .  .  .
+2  P  P  P  P [.] P  P  P
+1  R  N  B  Q  K  B  N  R
+   a  b  c  d  e  f  g  h
+Black to move
+");
+    }
+
+    #[test]
+    fn test_diagram_options() {
+        let game = Game::from_fen("7k/8/8/8/8/8/8/KR6 w - - 0 1").unwrap();
+        let bare = DiagramOptions { coordinates: false, side_to_move: false, ..DiagramOptions::default() };
+        assert_eq!(game.diagram(bare).lines().next(), Some(" .  .  .  .  .  .  .  k"));
+
+        let flipped = game.diagram(DiagramOptions { flipped: true, unicode: true, ..DiagramOptions::default() });
+        let lines: Vec<&str> = flipped.lines().collect();
+        assert_eq!(lines[0], "1  ·  ·  ·  ·  ·  ·  ♖  ♔");
+        assert_eq!(lines[7], "8  ♚  ·  ·  ·  ·  ·  ·  ·");
+        assert_eq!(lines[8], "   h  g  f  e  d  c  b  a");
+        assert_eq!(lines[9], "White to move");
+        assert_eq!(format!("{:#}", game), game.diagram(DiagramOptions { unicode: true, ..DiagramOptions::default() }));
+
+        let mut mated = Game::from_fen("7k/8/6K1/8/8/8/8/R7 w - - 0 1").unwrap();
+        mated.make_move("a1a8").unwrap();
+        let diagram = mated.to_string();
+        let lines: Vec<&str> = diagram.lines().collect();
+        assert_eq!(lines[0], "8 [R] .  .  .  .  .  .  k");
+        assert_eq!(lines[7], "1 [.] .  .  .  .  .  .  .");
+        assert_eq!(lines[9], "White wins by checkmate");
+    }
+}
@@ -0,0 +1,237 @@
+//! Network input encodings. `Encoding::Pieces` is `Game::encode`, piece placement only;
+//! `Encoding::AlphaZero` adds auxiliary planes in the style of AlphaZero so the network can
+//! see whose move it is, the castling rights, en passant and how close a draw by repetition
+//! or the fifty-move rule is. `Encoding::Features` adds the positional features of
+//! `Game::feature_planes` to the pieces. Any of them can be read from White's point of view
+//! or from the side to move's, see `Perspective`.
+
+use chess::{Color, File, Rank, Square};
+use super::{Game, Player, Variant, FEATURE_PLANES};
+
+/// Values per square in the `AlphaZero` encoding: 12 piece planes (White's pawns, bishops,
+/// knights, rooks, queens and king, then Black's), side to move, four castling rights, en
+/// passant, two repetition planes and the halfmove clock.
+pub const ALPHA_ZERO_PLANES: usize = 21;
+
+/// Which inputs a model is trained on. All are square-major, a1 to h8, with the variant
+/// inputs of `Variant::input_size` appended.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Encoding {
+    /// Six values per square, +1 for a White piece and -1 for a Black one.
+    #[default]
+    Pieces,
+    /// `ALPHA_ZERO_PLANES` values per square, all 0 or 1 apart from the halfmove clock.
+    AlphaZero,
+    /// `Pieces`, followed by the `FEATURE_PLANES` values per square of `Game::feature_planes`.
+    Features,
+}
+
+/// Whose point of view a model sees positions and gives its value from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Perspective {
+    /// White's: the board as it stands, and a value that is White's score.
+    #[default]
+    White,
+    /// The side to move's: with Black to move the board is mirrored rank for rank and the
+    /// colours swapped, so the mover's pieces always come first and move up the board, and
+    /// the value is the mover's score. The AlphaZero side-to-move plane still gives the
+    /// actual colour.
+    SideToMove,
+}
+
+impl Encoding {
+    /// Length of `Game::encode_as` for this encoding and `variant`.
+    pub fn input_size(self, variant: Variant) -> usize {
+        let board = match self {
+            Encoding::Pieces => 64 * 6,
+            Encoding::AlphaZero => 64 * ALPHA_ZERO_PLANES,
+            Encoding::Features => 64 * (6 + FEATURE_PLANES),
+        };
+        variant.input_size() - 64 * 6 + board
+    }
+}
+
+impl Game {
+    /// The position as network inputs in the given encoding, from White's point of view.
+    pub fn encode_as(&self, encoding: Encoding) -> Vec<f32> {
+        self.encode_for(encoding, Perspective::White)
+    }
+
+    /// The position as network inputs in the given encoding and perspective.
+    pub fn encode_for(&self, encoding: Encoding, perspective: Perspective) -> Vec<f32> {
+        let us = match perspective {
+            Perspective::White => Color::White,
+            Perspective::SideToMove => self.current_player().into(),
+        };
+        let mut encoded = match encoding {
+            Encoding::Pieces => self.piece_planes(us),
+            Encoding::AlphaZero => self.alpha_zero_planes(us),
+            Encoding::Features => {
+                let mut encoded = self.piece_planes(us);
+                let features = self.feature_planes_for(us);
+                for i in 0..64 {
+                    let start = source_square(i / 8, i % 8, us).to_index() * FEATURE_PLANES;
+                    encoded.extend_from_slice(&features[start..start + FEATURE_PLANES]);
+                }
+                encoded
+            }
+        };
+        encoded.extend(self.variant_features(us));
+        encoded
+    }
+
+    /// `encode_piece` for every square, with `us`'s pieces positive.
+    fn piece_planes(&self, us: Color) -> Vec<f32> {
+        let sign = if us == Color::White { 1.0 } else { -1.0 };
+        let mut encoded = Vec::with_capacity(8*8*6);
+
+        // Match Python indexing: row=0 = rank0 (a1 row), row=7 = rank7 (a8 row)
+        // column=0 = file a, column=7 = file h
+        for row in 0..8 {
+            for column in 0..8 {
+                let piece_vec = self.encode_piece(source_square(row, column, us));
+                encoded.extend(piece_vec.map(|v| sign * v));
+            }
+        }
+        encoded
+    }
+
+    fn alpha_zero_planes(&self, us: Color) -> Vec<f32> {
+        let flag = |on: bool| if on { 1.0 } else { 0.0 };
+        let white_to_move = flag(self.current_player() == Player::White);
+        let castling = [us, !us].map(|color| self.castle_rights(color));
+        let en_passant = self.en_passant_target();
+        let repetitions = self.positions.get(&self.get_hash()).copied().unwrap_or(1);
+        let no_progress = self.halfmove_clock.min(100) as f32 / 100.0;
+
+        let mut encoded = Vec::with_capacity(64 * ALPHA_ZERO_PLANES);
+        for (i, pieces) in self.piece_planes(us).chunks(6).enumerate() {
+            let square = source_square(i / 8, i % 8, us);
+            encoded.extend(pieces.iter().map(|v| v.max(0.0)));
+            encoded.extend(pieces.iter().map(|v| (-v).max(0.0)));
+            encoded.push(white_to_move);
+            for rights in castling {
+                encoded.push(flag(rights.has_kingside()));
+                encoded.push(flag(rights.has_queenside()));
+            }
+            encoded.push(flag(en_passant == Some(square)));
+            encoded.push(flag(repetitions >= 2));
+            encoded.push(flag(repetitions >= 3));
+            encoded.push(no_progress);
+        }
+        encoded
+    }
+
+    /// The en passant square as `to_fen` writes it.
+    pub(super) fn en_passant_target(&self) -> Option<Square> {
+        match &self.antichess {
+            Some(position) => position.en_passant(),
+            None => self.board.en_passant()?.forward(self.board.side_to_move()),
+        }
+    }
+}
+
+/// The square whose contents are encoded at `row` and `column` when the board is seen from
+/// `us`'s side: row 0 is `us`'s back rank.
+fn source_square(row: usize, column: usize, us: Color) -> Square {
+    let rank = if us == Color::White { row } else { 7 - row };
+    Square::make_square(Rank::from_index(rank), File::from_index(column))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The `ALPHA_ZERO_PLANES` values for `square`.
+    fn planes(encoded: &[f32], square: Square) -> &[f32] {
+        let start = square.to_index() * ALPHA_ZERO_PLANES;
+        &encoded[start..start + ALPHA_ZERO_PLANES]
+    }
+
+    #[test]
+    fn test_alpha_zero_encoding_initial_position() {
+        let game = Game::new();
+        let encoded = game.encode_as(Encoding::AlphaZero);
+        assert_eq!(encoded.len(), 64 * ALPHA_ZERO_PLANES);
+        assert_eq!(game.encode_as(Encoding::Pieces), game.encode());
+
+        // Pieces, side to move, castling, en passant, repetitions, halfmove clock.
+        let aux = [1.0, 1.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 0.0];
+        let white_rook = [[0.0, 0.0, 0.0, 1.0, 0.0, 0.0], [0.0; 6]].concat();
+        assert_eq!(planes(&encoded, Square::A1), [white_rook, aux.to_vec()].concat(), "a1 should be a white rook");
+        let black_king = [[0.0; 6], [0.0, 0.0, 0.0, 0.0, 0.0, 1.0]].concat();
+        assert_eq!(planes(&encoded, Square::E8), [black_king, aux.to_vec()].concat(), "e8 should be a black king");
+        assert_eq!(planes(&encoded, Square::E4), [vec![0.0; 12], aux.to_vec()].concat(), "e4 should be empty");
+    }
+
+    #[test]
+    fn test_alpha_zero_auxiliary_planes() {
+        let mut game = Game::new();
+        for uci in ["e2e4", "g8f6", "e4e5", "d7d5"] {
+            game.make_move(uci).unwrap();
+        }
+        let en_passant_plane = |encoded: &[f32]| -> Vec<f32> {
+            encoded.iter().skip(17).step_by(ALPHA_ZERO_PLANES).copied().collect()
+        };
+        let encoded = game.encode_as(Encoding::AlphaZero);
+        assert_eq!(planes(&encoded, Square::D6)[17], 1.0, "exd6 is possible, so d6 is marked.");
+        assert_eq!(en_passant_plane(&encoded).iter().sum::<f32>(), 1.0);
+
+        game.make_move("e1e2").unwrap();
+        let encoded = game.encode_as(Encoding::AlphaZero);
+        let e4 = planes(&encoded, Square::E4);
+        assert_eq!(e4[12], 0.0, "Black to move.");
+        assert_eq!(&e4[13..17], &[0.0, 0.0, 1.0, 1.0], "White has given up castling.");
+        assert_eq!(e4[20], 1.0 / 100.0);
+        assert!(en_passant_plane(&encoded).iter().all(|&v| v == 0.0), "The chance has passed.");
+
+        let mut game = Game::new();
+        for uci in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+            game.make_move(uci).unwrap();
+        }
+        let encoded = game.encode_as(Encoding::AlphaZero);
+        assert_eq!(&planes(&encoded, Square::A1)[18..], &[1.0, 0.0, 4.0 / 100.0]);
+    }
+
+    #[test]
+    fn test_side_to_move_perspective() {
+        let mut game = Game::new();
+        for uci in ["g1f3", "d7d5", "b1c3", "d5d4", "e2e4"] {
+            game.make_move(uci).unwrap();
+        }
+        assert_eq!(game.encode_for(Encoding::Pieces, Perspective::White), game.encode());
+
+        // Black to move: the inputs are those of the same position with colours swapped.
+        let mirrored = Game::from_fen("r1bqkb1r/pppp1ppp/2n2n2/3Pp3/8/8/PPP1PPPP/RNBQKBNR w KQkq e6 0 1").unwrap();
+        assert_eq!(game.encode_for(Encoding::Pieces, Perspective::SideToMove), mirrored.encode());
+        assert_eq!(game.encode_for(Encoding::Features, Perspective::SideToMove), mirrored.encode_as(Encoding::Features));
+        let encoded = game.encode_for(Encoding::AlphaZero, Perspective::SideToMove);
+        let expected = mirrored.encode_as(Encoding::AlphaZero);
+        for (i, (&value, &mirrored_value)) in encoded.iter().zip(&expected).enumerate() {
+            if i % ALPHA_ZERO_PLANES == 12 {
+                assert_eq!((value, mirrored_value), (0.0, 1.0), "The colour plane is not mirrored.");
+            } else {
+                assert_eq!(value, mirrored_value, "plane {} of square {}", i % ALPHA_ZERO_PLANES, i / ALPHA_ZERO_PLANES);
+            }
+        }
+
+        // White to move encodes the same either way; variant inputs follow the perspective.
+        let game = Game::from_fen_variant("4k3/8/8/8/8/8/8/4K3 b - - 2+3 0 1", Variant::ThreeCheck).unwrap();
+        assert_eq!(&game.encode()[384 + 5..], &[1.0 / 3.0, 0.0]);
+        assert_eq!(&game.encode_for(Encoding::Pieces, Perspective::SideToMove)[384 + 5..], &[0.0, 1.0 / 3.0]);
+        assert_eq!(Game::new().encode_for(Encoding::AlphaZero, Perspective::SideToMove), Game::new().encode_as(Encoding::AlphaZero));
+    }
+
+    #[test]
+    fn test_encoding_input_sizes() {
+        for variant in [Variant::Standard, Variant::ThreeCheck, Variant::Crazyhouse, Variant::Antichess] {
+            let game = Game::with_variant(variant);
+            for encoding in [Encoding::Pieces, Encoding::AlphaZero, Encoding::Features] {
+                assert_eq!(game.encode_as(encoding).len(), encoding.input_size(variant));
+            }
+        }
+        assert_eq!(Encoding::Pieces.input_size(Variant::ThreeCheck), Variant::ThreeCheck.input_size());
+    }
+}
@@ -0,0 +1,247 @@
+//! Positional features beyond piece placement: which squares each side attacks, mobility,
+//! pins, checks, passed pawns and pressure on the king. They feed handcrafted evaluation
+//! terms directly, or the network as extra input planes through `Encoding::Features`.
+
+use chess::{between, get_adjacent_files, get_bishop_moves, get_file, get_king_moves, get_knight_moves,
+            get_pawn_attacks, get_rook_moves, BitBoard, Color, Piece, Rank, Square, EMPTY};
+use super::{Game, Player};
+
+/// Values per square that `feature_planes` produces: squares attacked by White, squares
+/// attacked by Black, pinned pieces, checkers and passed pawns.
+pub const FEATURE_PLANES: usize = 5;
+
+/// Everything `Game::side_features` measures for one side.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SideFeatures {
+    /// Squares the side's pieces attack, whether or not anything stands there.
+    pub attacked: BitBoard,
+    pub mobility: u32,
+    /// The side's pieces pinned to its own king.
+    pub pinned: BitBoard,
+    pub passed_pawns: BitBoard,
+    /// Attacks by the other side on this side's king and the squares around it.
+    pub king_zone_attacks: u32,
+}
+
+impl Game {
+    /// All of `player`'s features at once.
+    pub fn side_features(&self, player: Player) -> SideFeatures {
+        SideFeatures {
+            attacked: self.attacked_squares(player),
+            mobility: self.mobility(player),
+            pinned: self.pinned_pieces(player),
+            passed_pawns: self.passed_pawns(player),
+            king_zone_attacks: self.king_zone_attacks(player),
+        }
+    }
+
+    /// Every square `player` attacks, counting x-rays only through empty squares. Pawns
+    /// attack diagonally whether or not there is anything to capture.
+    pub fn attacked_squares(&self, player: Player) -> BitBoard {
+        let color = player.into();
+        let occupied = self.occupied();
+        self.color_combined(color)
+            .fold(EMPTY, |attacked, sq| attacked | self.attacks_from(sq, color, occupied))
+    }
+
+    /// How many squares `player`'s knights, bishops, rooks and queens can move to, ignoring
+    /// pins and checks: the squares they attack that are not taken by `player`'s own pieces.
+    pub fn mobility(&self, player: Player) -> u32 {
+        let color = player.into();
+        let occupied = self.occupied();
+        let own = self.color_combined(color);
+        let movers = own & !self.pieces(Piece::Pawn) & !self.pieces(Piece::King);
+        movers.map(|sq| (self.attacks_from(sq, color, occupied) & !own).popcnt()).sum()
+    }
+
+    /// `player`'s pieces that cannot leave the line between their king and an enemy slider
+    /// without exposing the king. Empty in Antichess, where the king is not royal.
+    pub fn pinned_pieces(&self, player: Player) -> BitBoard {
+        let color: Color = player.into();
+        let Some(king) = self.royal_king(color) else {
+            return EMPTY;
+        };
+        let occupied = self.occupied();
+        let enemy = self.color_combined(!color);
+        let queens = self.pieces(Piece::Queen);
+        let snipers = (get_rook_moves(king, EMPTY) & (self.pieces(Piece::Rook) | queens) & enemy)
+            | (get_bishop_moves(king, EMPTY) & (self.pieces(Piece::Bishop) | queens) & enemy);
+
+        snipers.fold(EMPTY, |pinned, sniper| {
+            let blockers = between(king, sniper) & occupied;
+            if blockers.popcnt() == 1 && blockers & self.color_combined(color) != EMPTY {
+                pinned | blockers
+            } else {
+                pinned
+            }
+        })
+    }
+
+    /// The pieces giving check to the side to move.
+    pub fn checkers(&self) -> BitBoard {
+        let color: Color = self.current_player().into();
+        match self.royal_king(color) {
+            Some(king) => self.attackers_to(king, self.occupied()) & self.color_combined(!color),
+            None => EMPTY,
+        }
+    }
+
+    /// `player`'s pawns with no enemy pawn ahead of them on their own or a neighbouring file.
+    pub fn passed_pawns(&self, player: Player) -> BitBoard {
+        let color: Color = player.into();
+        let pawns = self.pieces(Piece::Pawn);
+        let enemy_pawns = pawns & self.color_combined(!color);
+        (pawns & self.color_combined(color))
+            .filter(|&sq| {
+                let files = get_file(sq.get_file()) | get_adjacent_files(sq.get_file());
+                files & ranks_ahead(sq.get_rank(), color) & enemy_pawns == EMPTY
+            })
+            .fold(EMPTY, |passed, sq| passed | BitBoard::from_square(sq))
+    }
+
+    /// How many times the other side's pieces attack `player`'s king square and the squares
+    /// next to it, counting a piece once for every zone square it hits. 0 in Antichess.
+    pub fn king_zone_attacks(&self, player: Player) -> u32 {
+        let color: Color = player.into();
+        let Some(king) = self.royal_king(color) else {
+            return 0;
+        };
+        let zone = get_king_moves(king) | BitBoard::from_square(king);
+        let occupied = self.occupied();
+        self.color_combined(!color)
+            .map(|sq| (self.attacks_from(sq, !color, occupied) & zone).popcnt())
+            .sum()
+    }
+
+    /// `FEATURE_PLANES` values for every square, a1 to h8 in the same order as `encode`:
+    /// attacked by White, attacked by Black, then pinned pieces, checkers and passed pawns
+    /// as 1.0 for White's pieces and -1.0 for Black's.
+    pub fn feature_planes(&self) -> Vec<f32> {
+        self.feature_planes_for(Color::White)
+    }
+
+    /// `feature_planes` with `us` in White's place: squares `us` attacks first, and `us`'s
+    /// pieces positive. The squares stay in a1 to h8 order.
+    pub(super) fn feature_planes_for(&self, us: Color) -> Vec<f32> {
+        let our_attacks = self.attacked_squares(us.into());
+        let their_attacks = self.attacked_squares((!us).into());
+        let pinned = self.pinned_pieces(Player::White) | self.pinned_pieces(Player::Black);
+        let checkers = self.checkers();
+        let passed = self.passed_pawns(Player::White) | self.passed_pawns(Player::Black);
+
+        let mut planes = Vec::with_capacity(64 * FEATURE_PLANES);
+        for sq in chess::ALL_SQUARES {
+            let bit = BitBoard::from_square(sq);
+            let sign = if self.color_on(sq) == Some(!us) { -1.0 } else { 1.0 };
+            let signed = |set: BitBoard| if set & bit != EMPTY { sign } else { 0.0 };
+            planes.extend([
+                if our_attacks & bit != EMPTY { 1.0 } else { 0.0 },
+                if their_attacks & bit != EMPTY { 1.0 } else { 0.0 },
+                signed(pinned),
+                signed(checkers),
+                signed(passed),
+            ]);
+        }
+        planes
+    }
+
+    /// The squares a `color` piece on `sq` attacks, with `occupied` blocking sliders.
+    fn attacks_from(&self, sq: Square, color: Color, occupied: BitBoard) -> BitBoard {
+        match self.piece_on(sq) {
+            Some(Piece::Pawn) => get_pawn_attacks(sq, color, !EMPTY),
+            Some(Piece::Knight) => get_knight_moves(sq),
+            Some(Piece::Bishop) => get_bishop_moves(sq, occupied),
+            Some(Piece::Rook) => get_rook_moves(sq, occupied),
+            Some(Piece::Queen) => get_bishop_moves(sq, occupied) | get_rook_moves(sq, occupied),
+            Some(Piece::King) => get_king_moves(sq),
+            None => EMPTY,
+        }
+    }
+
+    /// The king that can be checked and pinned against, if the variant has one.
+    fn royal_king(&self, color: Color) -> Option<Square> {
+        if self.antichess.is_some() {
+            return None;
+        }
+        let kings = self.pieces(Piece::King) & self.color_combined(color);
+        (kings != EMPTY).then(|| kings.to_square())
+    }
+}
+
+/// The ranks in front of `rank` from `color`'s point of view.
+fn ranks_ahead(rank: Rank, color: Color) -> BitBoard {
+    (0..8)
+        .filter(|&i| match color {
+            Color::White => i > rank.to_index(),
+            Color::Black => i < rank.to_index(),
+        })
+        .fold(EMPTY, |ranks, i| ranks | chess::get_rank(Rank::from_index(i)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Encoding;
+
+    fn squares(set: BitBoard) -> Vec<String> {
+        set.map(|sq| sq.to_string()).collect()
+    }
+
+    #[test]
+    fn test_attacks_and_mobility() {
+        let game = Game::new();
+        // Ranks 3 and 6 are all covered by pawns; the corners are attacked by nothing.
+        let white = game.attacked_squares(Player::White);
+        assert_eq!(white & chess::get_rank(Rank::Third), chess::get_rank(Rank::Third));
+        assert_eq!(white & BitBoard::from_square(Square::A1), EMPTY);
+        assert_eq!(white.popcnt(), 22);
+        assert_eq!(game.mobility(Player::White), 4, "Only the knights can move.");
+        assert_eq!(game.mobility(Player::Black), 4);
+
+        let game = Game::from_fen("4k3/8/8/8/3Q4/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(game.mobility(Player::White), 27);
+        assert_eq!(game.mobility(Player::Black), 0);
+    }
+
+    #[test]
+    fn test_pins_and_checkers() {
+        let game = Game::from_fen("4r1k1/8/8/1b6/8/3N4/4B3/4K3 b - - 0 1").unwrap();
+        assert_eq!(squares(game.pinned_pieces(Player::White)), ["e2"]);
+        assert_eq!(game.pinned_pieces(Player::Black), EMPTY);
+        assert_eq!(game.checkers(), EMPTY);
+
+        // Two enemy pieces in between is not a pin.
+        let game = Game::from_fen("4r1k1/8/4p3/8/8/8/4B3/4K3 w - - 0 1").unwrap();
+        assert_eq!(game.pinned_pieces(Player::White), EMPTY);
+
+        let game = Game::from_fen("4k3/8/8/8/1b6/3n4/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(squares(game.checkers()), ["d3", "b4"]);
+    }
+
+    #[test]
+    fn test_passed_pawns_and_king_zone() {
+        let game = Game::from_fen("4k3/7p/8/1P6/8/3p4/7P/4K3 w - - 0 1").unwrap();
+        assert_eq!(squares(game.passed_pawns(Player::White)), ["b5"]);
+        assert_eq!(squares(game.passed_pawns(Player::Black)), ["d3"]);
+
+        // The queen hits f7 and h7; the rook on a1 does not reach the king.
+        let game = Game::from_fen("6k1/5ppp/8/7Q/8/8/8/R5K1 w - - 0 1").unwrap();
+        assert_eq!(game.king_zone_attacks(Player::Black), 2);
+        assert_eq!(game.king_zone_attacks(Player::White), 0);
+        assert_eq!(game.side_features(Player::Black).king_zone_attacks, 2);
+    }
+
+    #[test]
+    fn test_feature_planes() {
+        let game = Game::from_fen("4r1k1/8/8/8/8/8/4B3/4K3 w - - 0 1").unwrap();
+        let planes = game.feature_planes();
+        assert_eq!(planes.len(), 64 * FEATURE_PLANES);
+        let at = |sq: Square| &planes[sq.to_index() * FEATURE_PLANES..(sq.to_index() + 1) * FEATURE_PLANES];
+        assert_eq!(at(Square::E2), &[1.0, 1.0, 1.0, 0.0, 0.0], "The bishop is pinned and defended.");
+        assert_eq!(at(Square::E8), &[0.0, 0.0, 0.0, 0.0, 0.0]);
+        let encoded = game.encode_as(Encoding::Features);
+        assert_eq!(encoded.len(), 384 + 64 * FEATURE_PLANES);
+        assert_eq!(&encoded[..384], &game.encode()[..]);
+        assert_eq!(&encoded[384..], &planes[..]);
+    }
+}
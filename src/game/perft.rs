@@ -0,0 +1,193 @@
+//! Perft: counting the leaf nodes of the move tree to a fixed depth, the standard way to
+//! check move generation against published results.
+
+use chess::{Board, MoveGen};
+use super::{Game, Move, Variant};
+
+/// Which moves `perft` and `divide` follow.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PerftRules {
+    /// Every legal move, ignoring repetition, move-count and material draws or any other
+    /// way the game may already have ended. This is what published perft numbers count.
+    MovesOnly,
+    /// Only the moves `Game::legal_moves` offers, so lines stop wherever our own
+    /// terminal rules end the game.
+    GameRules,
+}
+
+impl Game {
+    /// Number of leaf nodes `depth` plies below the current position.
+    pub fn perft(&self, depth: u32, rules: PerftRules) -> u64 {
+        match rules {
+            // Chess960 castling and variant rules are applied by the game, not the board.
+            PerftRules::MovesOnly if !self.is_chess960() && self.variant == Variant::Standard => {
+                perft_board(&self.board, depth)
+            }
+            _ => perft_game(&mut self.clone(), depth, rules),
+        }
+    }
+
+    /// Perft split by first move, for tracking down a miscount against a reference engine.
+    pub fn divide(&self, depth: u32, rules: PerftRules) -> Vec<(Move, u64)> {
+        if depth == 0 {
+            return Vec::new();
+        }
+
+        let mut game = self.clone();
+        moves(self, rules).into_iter().map(|mv| {
+            game.apply_move(mv);
+            let nodes = game.perft(depth - 1, rules);
+            game.undo_move();
+            (mv, nodes)
+        }).collect()
+    }
+}
+
+fn perft_board(board: &Board, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = MoveGen::new_legal(board);
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut next = *board;
+    moves.map(|mv| {
+        board.make_move(mv, &mut next);
+        perft_board(&next, depth - 1)
+    }).sum()
+}
+
+fn perft_game(game: &mut Game, depth: u32, rules: PerftRules) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = moves(game, rules);
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    moves.into_iter().map(|mv| {
+        game.apply_move(mv);
+        let nodes = perft_game(game, depth - 1, rules);
+        game.undo_move();
+        nodes
+    }).sum()
+}
+
+fn moves(game: &Game, rules: PerftRules) -> Vec<Move> {
+    match rules {
+        PerftRules::MovesOnly => game.generate_moves(),
+        PerftRules::GameRules => game.legal_moves(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_perft(fen: &str, expected: &[u64]) {
+        let game = Game::from_fen(fen).expect("Should parse fen");
+        for (depth, &nodes) in expected.iter().enumerate() {
+            let depth = depth as u32 + 1;
+            assert_eq!(game.perft(depth, PerftRules::MovesOnly), nodes, "perft({}) of {}", depth, fen);
+        }
+    }
+
+    #[test]
+    fn test_perft_initial_position() {
+        assert_perft("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", &[20, 400, 8902, 197281]);
+        assert_eq!(Game::new().perft(0, PerftRules::MovesOnly), 1);
+    }
+
+    #[test]
+    fn test_perft_kiwipete() {
+        assert_perft("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", &[48, 2039, 97862]);
+    }
+
+    #[test]
+    fn test_perft_positions_3_to_6() {
+        assert_perft("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", &[14, 191, 2812, 43238, 674624]);
+        assert_perft("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", &[6, 264, 9467, 422333]);
+        assert_perft("r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1", &[6, 264, 9467, 422333]);
+        assert_perft("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", &[44, 1486, 62379]);
+        assert_perft("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", &[46, 2079, 89890]);
+    }
+
+    #[test]
+    fn test_perft_promotions() {
+        assert_perft("n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1", &[24, 496, 9483, 182838]);
+        // Promoting out of check, and promoting (or under-promoting) to give check.
+        assert_eq!(Game::from_fen("2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1").unwrap().perft(6, PerftRules::MovesOnly), 3821001);
+        assert_eq!(Game::from_fen("4k3/1P6/8/8/8/8/K7/8 w - - 0 1").unwrap().perft(6, PerftRules::MovesOnly), 217342);
+        assert_eq!(Game::from_fen("8/P1k5/K7/8/8/8/8/8 w - - 0 1").unwrap().perft(6, PerftRules::MovesOnly), 92683);
+    }
+
+    #[test]
+    fn test_perft_en_passant_edge_cases() {
+        // En passant would expose the king along the rank: the capture is illegal.
+        assert_eq!(Game::from_fen("8/5bk1/8/2Pp4/8/1K6/8/8 w - d6 0 1").unwrap().perft(6, PerftRules::MovesOnly), 824064);
+        assert_eq!(Game::from_fen("8/8/1k6/8/2pP4/8/5BK1/8 b - d3 0 1").unwrap().perft(6, PerftRules::MovesOnly), 824064);
+        // En passant capture gives check.
+        assert_eq!(Game::from_fen("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1").unwrap().perft(6, PerftRules::MovesOnly), 1440467);
+        assert_eq!(Game::from_fen("8/5k2/8/2Pp4/2B5/1K6/8/8 w - d6 0 1").unwrap().perft(6, PerftRules::MovesOnly), 1440467);
+    }
+
+    #[test]
+    fn test_perft_castling_edge_cases() {
+        // Castling that gives check.
+        assert_eq!(Game::from_fen("5k2/8/8/8/8/8/8/4K2R w K - 0 1").unwrap().perft(6, PerftRules::MovesOnly), 661072);
+        assert_eq!(Game::from_fen("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap().perft(6, PerftRules::MovesOnly), 803711);
+        // Castling rights lost when rooks are captured, and castling through attacked squares.
+        assert_eq!(Game::from_fen("r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1").unwrap().perft(4, PerftRules::MovesOnly), 1274206);
+        assert_eq!(Game::from_fen("r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1").unwrap().perft(4, PerftRules::MovesOnly), 1720476);
+    }
+
+    #[test]
+    fn test_perft_stalemate_and_checkmate() {
+        assert_eq!(Game::from_fen("K1k5/8/P7/8/8/8/8/8 w - - 0 1").unwrap().perft(6, PerftRules::MovesOnly), 2217);
+        assert_eq!(Game::from_fen("8/8/2k5/5q2/5n2/8/5K2/8 b - - 0 1").unwrap().perft(4, PerftRules::MovesOnly), 23527);
+    }
+
+    #[test]
+    fn test_perft_game_rules_match_move_generation() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ] {
+            let game = Game::from_fen(fen).unwrap();
+            assert_eq!(game.perft(3, PerftRules::GameRules), game.perft(3, PerftRules::MovesOnly), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_perft_game_rules_stop_at_draws() {
+        // Kings only: a draw by insufficient material, though the kings can still move.
+        let game = Game::from_fen("8/8/8/8/8/6K1/8/2k5 w - - 0 1").unwrap();
+        assert_eq!(game.perft(1, PerftRules::MovesOnly), 8);
+        assert_eq!(game.perft(1, PerftRules::GameRules), 0);
+
+        // One move from the fifty-move rule: only capturing the rook resets the clock.
+        let game = Game::from_fen("4k3/8/8/8/8/8/7r/4K2R w - - 99 80").unwrap();
+        assert_eq!(game.perft(2, PerftRules::MovesOnly), 79);
+        assert_eq!(game.perft(2, PerftRules::GameRules), 5);
+    }
+
+    #[test]
+    fn test_divide() {
+        let game = Game::new();
+        let split = game.divide(2, PerftRules::MovesOnly);
+        assert_eq!(split.len(), 20);
+        assert!(split.iter().all(|&(_, nodes)| nodes == 20));
+        assert_eq!(split.iter().map(|&(_, nodes)| nodes).sum::<u64>(), game.perft(2, PerftRules::MovesOnly));
+
+        let split = Game::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+            .unwrap()
+            .divide(2, PerftRules::GameRules);
+        let castle = split.iter().find(|(mv, _)| mv.to_string() == "e1g1").expect("O-O is legal");
+        assert_eq!(castle.1, 43);
+        assert_eq!(split.iter().map(|&(_, nodes)| nodes).sum::<u64>(), 2039);
+    }
+}
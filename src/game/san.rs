@@ -0,0 +1,327 @@
+//! Standard Algebraic Notation (SAN) for moves on a `Game`, e.g. `Nf3`, `exd5`, `O-O`, `e8=Q+`,
+//! and Crazyhouse drops such as `N@f3`.
+
+use chess::{CastleRights, ChessMove, File, Piece, Rank, Square};
+use crate::error::Error;
+use super::{Game, Move};
+
+impl Game {
+    /// Writes a legal move in SAN, with the minimal disambiguation and a `+`/`#` suffix.
+    pub fn to_san(&self, mv: Move) -> Result<String, Error> {
+        if !self.is_legal(mv) {
+            return Err(Error::IllegalMove(mv.to_string()));
+        }
+
+        let mut san = String::new();
+        if let Some(piece) = mv.dropped_piece() {
+            if piece != Piece::Pawn {
+                san.push(piece_letter(piece));
+            }
+            san.push('@');
+            san.push_str(&mv.to().to_string());
+            self.push_check_suffix(mv, &mut san);
+            return Ok(san);
+        }
+
+        let chess_move = ChessMove::from(mv);
+        let source = mv.source();
+        let dest = mv.to();
+        let piece = self.piece_on(source).expect("legal move starts on a piece");
+
+        if let Some(side) = self.castling_side(chess_move) {
+            san.push_str(if side == CastleRights::KingSide { "O-O" } else { "O-O-O" });
+        } else if piece == Piece::Pawn {
+            if source.get_file() != dest.get_file() {
+                san.push(file_char(source.get_file()));
+                san.push('x');
+            }
+            san.push_str(&dest.to_string());
+            if let Some(promotion) = mv.promotion() {
+                san.push('=');
+                san.push(piece_letter(promotion));
+            }
+        } else {
+            san.push(piece_letter(piece));
+            san.push_str(&self.disambiguation(mv, piece));
+            if self.piece_on(dest).is_some() {
+                san.push('x');
+            }
+            san.push_str(&dest.to_string());
+        }
+
+        self.push_check_suffix(mv, &mut san);
+        Ok(san)
+    }
+
+    /// Reads a SAN move for the side to move. Check, mate and annotation suffixes are
+    /// ignored, `x` is optional and castling may be written with zeros (`0-0`).
+    pub fn parse_san(&self, san: &str) -> Result<Move, Error> {
+        let text = san.trim().trim_end_matches(['+', '#', '!', '?']);
+        if text.is_empty() {
+            return Err(Error::MalformedMove("empty SAN move".to_string()));
+        }
+
+        let castle_side = match text {
+            "O-O" | "0-0" => Some(CastleRights::KingSide),
+            "O-O-O" | "0-0-0" => Some(CastleRights::QueenSide),
+            _ => None,
+        };
+        if let Some(side) = castle_side {
+            return self.castling_move(side)
+                .map(Into::into)
+                .ok_or_else(|| Error::IllegalMove(san.to_string()));
+        }
+
+        // Crazyhouse drops: `N@f3`, with the pawn written `P@e6` or just `@e6`.
+        if let Some((piece_text, square_text)) = text.split_once('@') {
+            let piece = match piece_text {
+                "" | "P" => Piece::Pawn,
+                "N" => Piece::Knight,
+                "B" => Piece::Bishop,
+                "R" => Piece::Rook,
+                "Q" => Piece::Queen,
+                _ => return Err(Error::MalformedMove(format!("'{}' is not SAN", san))),
+            };
+            let dest = square_text.parse::<Square>()
+                .map_err(|_| Error::MalformedMove(format!("bad destination square in '{}'", san)))?;
+            let mv = Move::new_drop(piece, dest);
+            return if self.is_legal(mv) { Ok(mv) } else { Err(Error::IllegalMove(san.to_string())) };
+        }
+
+        let mut chars: Vec<char> = text.chars().collect();
+        let piece = match chars[0] {
+            'N' => Piece::Knight,
+            'B' => Piece::Bishop,
+            'R' => Piece::Rook,
+            'Q' => Piece::Queen,
+            'K' => Piece::King,
+            _ => Piece::Pawn,
+        };
+        if piece != Piece::Pawn {
+            chars.remove(0);
+        }
+
+        // Promotion is written `e8=Q`, but `e8Q` turns up in the wild too.
+        let mut promotion = None;
+        if piece == Piece::Pawn {
+            if let Some(&last) = chars.last() {
+                if let Some(p) = promotion_piece(last) {
+                    promotion = Some(p);
+                    chars.pop();
+                    if chars.last() == Some(&'=') {
+                        chars.pop();
+                    }
+                }
+            }
+        }
+
+        if chars.len() < 2 {
+            return Err(Error::MalformedMove(format!("'{}' is not SAN", san)));
+        }
+        let dest_text: String = chars[chars.len() - 2..].iter().collect();
+        let dest = dest_text.parse::<Square>()
+            .map_err(|_| Error::MalformedMove(format!("bad destination square in '{}'", san)))?;
+
+        let mut from_file = None;
+        let mut from_rank = None;
+        for &c in &chars[..chars.len() - 2] {
+            match c {
+                'a'..='h' => from_file = Some(File::from_index(c as usize - 'a' as usize)),
+                '1'..='8' => from_rank = Some(Rank::from_index(c as usize - '1' as usize)),
+                'x' | ':' | '-' => {}
+                _ => return Err(Error::MalformedMove(format!("'{}' is not SAN", san))),
+            }
+        }
+
+        let mut candidates = self.generate_moves().into_iter().filter(|m| {
+            m.dropped_piece().is_none()
+                && m.to() == dest
+                && self.piece_on(m.source()) == Some(piece)
+                && m.promotion() == promotion
+                && from_file.is_none_or(|f| m.source().get_file() == f)
+                && from_rank.is_none_or(|r| m.source().get_rank() == r)
+                && self.castling_side(m.mv).is_none()
+        });
+
+        match (candidates.next(), candidates.next()) {
+            (Some(mv), None) => Ok(mv),
+            (Some(_), Some(_)) => Err(Error::MalformedMove(format!("ambiguous SAN move '{}'", san))),
+            (None, _) => Err(Error::IllegalMove(san.to_string())),
+        }
+    }
+
+    /// Parses and plays a SAN move, mirroring `make_move` for coordinate notation.
+    pub fn make_san_move(&mut self, san: &str) -> Result<Self, Error> {
+        let parsed_move = self.parse_san(san)?;
+        self.apply_move(parsed_move);
+
+        Ok(self.clone())
+    }
+
+    /// The file and/or rank needed to tell `mv` apart from other legal moves of the
+    /// same piece type to the same square.
+    fn disambiguation(&self, mv: Move, piece: Piece) -> String {
+        let source = mv.source();
+        let others: Vec<Square> = self.generate_moves()
+            .into_iter()
+            .filter(|m| m.dropped_piece().is_none() && m.to() == mv.to() && m.source() != source)
+            .filter(|m| self.piece_on(m.source()) == Some(piece))
+            .map(|m| m.source())
+            .collect();
+
+        if others.is_empty() {
+            String::new()
+        } else if others.iter().all(|sq| sq.get_file() != source.get_file()) {
+            file_char(source.get_file()).to_string()
+        } else if others.iter().all(|sq| sq.get_rank() != source.get_rank()) {
+            rank_char(source.get_rank()).to_string()
+        } else {
+            source.to_string()
+        }
+    }
+
+    /// Appends `+` or `#` if `mv` gives check. Mate is decided on the game rather than the
+    /// bare board, since in Crazyhouse a check can also be met by a drop.
+    fn push_check_suffix(&self, mv: Move, san: &mut String) {
+        // Antichess has no check.
+        if self.antichess.is_some() || self.board_after(mv).checkers().popcnt() == 0 {
+            return;
+        }
+        let mut after = self.clone();
+        after.apply_move(mv);
+        san.push(if after.generate_moves().is_empty() { '#' } else { '+' });
+    }
+}
+
+fn piece_letter(piece: Piece) -> char {
+    match piece {
+        Piece::Pawn => 'P',
+        Piece::Knight => 'N',
+        Piece::Bishop => 'B',
+        Piece::Rook => 'R',
+        Piece::Queen => 'Q',
+        Piece::King => 'K',
+    }
+}
+
+fn promotion_piece(c: char) -> Option<Piece> {
+    match c.to_ascii_uppercase() {
+        'N' => Some(Piece::Knight),
+        'B' => Some(Piece::Bishop),
+        'R' => Some(Piece::Rook),
+        'Q' => Some(Piece::Queen),
+        'K' => Some(Piece::King),
+        _ => None,
+    }
+}
+
+fn file_char(file: File) -> char {
+    (b'a' + file.to_index() as u8) as char
+}
+
+fn rank_char(rank: Rank) -> char {
+    (b'1' + rank.to_index() as u8) as char
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mv(uci: &str) -> Move {
+        uci.parse().expect("test move should parse")
+    }
+
+    fn san_of(fen: &str, uci: &str) -> String {
+        let game = Game::from_fen(fen).expect("Should parse fen");
+        game.to_san(mv(uci)).expect("Move should be legal")
+    }
+
+    #[test]
+    fn test_san_basic_moves() {
+        let game = Game::new();
+        assert_eq!(game.to_san(mv("e2e4")).unwrap(), "e4");
+        assert_eq!(game.to_san(mv("g1f3")).unwrap(), "Nf3");
+        assert!(game.to_san(mv("e2e5")).is_err(), "Illegal moves have no SAN.");
+    }
+
+    #[test]
+    fn test_san_captures_and_en_passant() {
+        let fen = "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3";
+        assert_eq!(san_of(fen, "e5f6"), "exf6");
+        assert_eq!(san_of("4k3/8/8/3p4/4N3/8/8/4K3 w - - 0 1", "e4d6"), "Nd6+");
+        assert_eq!(san_of("4k3/8/8/3p4/4N3/8/8/4K3 w - - 0 1", "e4f6"), "Nf6+");
+        assert_eq!(san_of("4k3/8/2p5/8/4N3/8/8/4K3 w - - 0 1", "e4c5"), "Nc5");
+        assert_eq!(san_of("4k3/8/8/3p4/4B3/8/8/4K3 w - - 0 1", "e4d5"), "Bxd5");
+    }
+
+    #[test]
+    fn test_san_disambiguation() {
+        // Knights on b1 and f3 can both reach d2: disambiguate by file.
+        assert_eq!(san_of("4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1", "b1d2"), "Nbd2");
+        // Rooks on a1 and a5 both reach a3: disambiguate by rank.
+        assert_eq!(san_of("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "a1a3"), "R1a3");
+        // Queens on e4, h4 and h1 all reach e1: the h4 queen needs file and rank.
+        assert_eq!(san_of("1k6/8/8/8/4Q2Q/8/8/K6Q w - - 0 1", "h4e1"), "Qh4e1");
+        assert_eq!(san_of("1k6/8/8/8/4Q2Q/8/8/K6Q w - - 0 1", "e4e1"), "Qee1");
+        // A pinned knight cannot move, so the other knight needs no disambiguation.
+        assert_eq!(san_of("4k1r1/8/8/8/8/2N3N1/8/6K1 w - - 0 1", "c3e4"), "Ne4");
+        assert_eq!(san_of("4k3/4r3/8/8/8/8/2N1N3/4K3 w - - 0 1", "c2d4"), "Nd4");
+    }
+
+    #[test]
+    fn test_san_castling_promotion_and_mate() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(san_of(fen, "e1g1"), "O-O");
+        assert_eq!(san_of(fen, "e1c1"), "O-O-O");
+
+        let fen = "3rk3/4P3/8/8/8/8/8/4K3 w - - 0 1";
+        assert_eq!(san_of(fen, "e7d8q"), "exd8=Q+");
+        assert_eq!(san_of(fen, "e7d8n"), "exd8=N");
+        assert_eq!(san_of("8/4P3/8/8/8/8/k7/4K3 w - - 0 1", "e7e8q"), "e8=Q");
+        assert_eq!(san_of("4k3/R7/8/8/8/8/8/4K2R w - - 0 1", "h1h8"), "Rh8#");
+
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4";
+        assert_eq!(san_of(fen, "h5f7"), "Qxf7#");
+    }
+
+    #[test]
+    fn test_parse_san() {
+        let game = Game::new();
+        assert_eq!(game.parse_san("e4").unwrap(), mv("e2e4"));
+        assert_eq!(game.parse_san("Nf3").unwrap(), mv("g1f3"));
+        assert_eq!(game.parse_san("Nf3!?").unwrap(), mv("g1f3"));
+        assert!(game.parse_san("Ne4").is_err(), "No knight can reach e4.");
+        assert!(game.parse_san("Zz9").is_err());
+        assert!(game.parse_san("").is_err());
+
+        let game = Game::from_fen("4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1").unwrap();
+        assert!(game.parse_san("Nd2").is_err(), "Nd2 is ambiguous.");
+        assert_eq!(game.parse_san("Nbd2").unwrap(), mv("b1d2"));
+        assert_eq!(game.parse_san("Nfd2").unwrap(), mv("f3d2"));
+        assert_eq!(game.parse_san("Nf3d2").unwrap(), mv("f3d2"));
+
+        let game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(game.parse_san("O-O").unwrap(), mv("e1g1"));
+        assert_eq!(game.parse_san("0-0-0").unwrap(), mv("e1c1"));
+        assert!(game.parse_san("Kg1").is_err(), "Castling must be written as O-O.");
+
+        let game = Game::from_fen("3r3k/4P3/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(game.parse_san("exd8=Q+").unwrap(), mv("e7d8q"));
+        assert_eq!(game.parse_san("exd8N").unwrap(), mv("e7d8n"));
+        assert_eq!(game.parse_san("e8=R").unwrap(), mv("e7e8r"));
+        assert!(game.parse_san("e8").is_err(), "A promotion must name its piece.");
+    }
+
+    #[test]
+    fn test_san_round_trip_over_a_game() {
+        let mut game = Game::new();
+        let sans = ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Bxc6", "dxc6", "O-O", "f6",
+            "d4", "exd4", "Nxd4", "c5", "Nb3", "Qxd1", "Rxd1", "Bg4", "f3", "Be6"];
+        for san in sans {
+            let parsed = game.parse_san(san).expect("SAN should parse");
+            assert_eq!(game.to_san(parsed).unwrap(), san);
+            game.make_san_move(san).expect("SAN move should be legal");
+        }
+        assert_eq!(game.history().len(), sans.len());
+    }
+}
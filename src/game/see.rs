@@ -0,0 +1,187 @@
+//! Static Exchange Evaluation (SEE): the material a sequence of captures on one square wins
+//! or loses, when both sides always recapture with their least valuable piece and may stop
+//! whenever carrying on would cost them. Sliders lined up behind each other (x-rays) join in
+//! as the pieces in front leave the square. Pins are not taken into account.
+
+use chess::{get_bishop_moves, get_king_moves, get_knight_moves, get_pawn_attacks, get_rook_moves,
+            BitBoard, Color, Piece, Square, EMPTY};
+use super::{Game, Move};
+
+/// Piece values in centipawns, indexed by `Piece::to_index`. The king's value only needs to
+/// be larger than anything it could win.
+pub(crate) const PIECE_VALUES: [i32; 6] = [100, 320, 330, 500, 900, 20000];
+
+/// Cheapest first, for picking the least valuable attacker.
+const BY_VALUE: [Piece; 6] = [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen, Piece::King];
+
+impl Game {
+    /// The material `mv` wins for the side playing it, in centipawns, once the exchange on
+    /// its destination square is played out: positive for a winning capture, negative if
+    /// the moving piece is lost for less. Quiet moves score 0 unless the piece is left
+    /// hanging, and castling always scores 0. `mv` should be legal in the position.
+    pub fn see(&self, mv: Move) -> i32 {
+        let us: Color = self.current_player().into();
+        let to = mv.to();
+        if mv.dropped_piece().is_none() && self.castling_side(mv.mv).is_some() {
+            return 0;
+        }
+
+        let (mover, mut occupied) = match mv.dropped_piece() {
+            Some(piece) => (piece, self.occupied()),
+            None => match self.piece_on(mv.source()) {
+                Some(piece) => (piece, self.occupied() ^ BitBoard::from_square(mv.source())),
+                None => return 0,
+            },
+        };
+
+        let mut captured = self.piece_on(to).map_or(0, value);
+        if mover == Piece::Pawn && self.piece_on(to).is_none() && mv.source().get_file() != to.get_file() {
+            // En passant: the captured pawn leaves a square that may open a line.
+            captured = value(Piece::Pawn);
+            occupied ^= BitBoard::from_square(Square::make_square(mv.source().get_rank(), to.get_file()));
+        }
+        let (on_square, promotion_gain) = match mv.promotion() {
+            Some(piece) => (piece, value(piece) - value(Piece::Pawn)),
+            None => (mover, 0),
+        };
+
+        self.exchange(to, on_square, captured + promotion_gain, occupied, !us)
+    }
+
+    /// Whether `mv` wins at least `threshold` centipawns by SEE, e.g. `see_ge(mv, 0)` for
+    /// captures worth searching in quiescence.
+    pub fn see_ge(&self, mv: Move, threshold: i32) -> bool {
+        self.see(mv) >= threshold
+    }
+
+    /// The SEE value of the side to move capturing on `sq` with its least valuable attacker,
+    /// or `None` if there is nothing of the opponent's there or nothing attacks it.
+    pub fn see_square(&self, sq: Square) -> Option<i32> {
+        let us: Color = self.current_player().into();
+        if self.color_on(sq) != Some(!us) {
+            return None;
+        }
+        let occupied = self.occupied();
+        let attackers = self.attackers_to(sq, occupied) & self.color_combined(us);
+        let (from, piece) = self.least_valuable(attackers)?;
+        let captured = value(self.piece_on(sq)?);
+        Some(self.exchange(sq, piece, captured, occupied ^ BitBoard::from_square(from), !us))
+    }
+
+    /// Every piece of either colour attacking `sq`, with `occupied` as the blockers. Only
+    /// pieces still in `occupied` are returned.
+    pub(super) fn attackers_to(&self, sq: Square, occupied: BitBoard) -> BitBoard {
+        let white_pawns = self.pieces(Piece::Pawn) & self.color_combined(Color::White);
+        let black_pawns = self.pieces(Piece::Pawn) & self.color_combined(Color::Black);
+        let diagonal = self.pieces(Piece::Bishop) | self.pieces(Piece::Queen);
+        let straight = self.pieces(Piece::Rook) | self.pieces(Piece::Queen);
+
+        let attackers = get_pawn_attacks(sq, Color::Black, white_pawns)
+            | get_pawn_attacks(sq, Color::White, black_pawns)
+            | (get_knight_moves(sq) & self.pieces(Piece::Knight))
+            | (get_king_moves(sq) & self.pieces(Piece::King))
+            | (get_bishop_moves(sq, occupied) & diagonal)
+            | (get_rook_moves(sq, occupied) & straight);
+        attackers & occupied
+    }
+
+    /// Plays out the exchange on `sq`, where `on_square` has just captured something worth
+    /// `captured` and `side` is to recapture, and returns the result for the side that began.
+    fn exchange(&self, sq: Square, mut on_square: Piece, captured: i32, mut occupied: BitBoard, mut side: Color) -> i32 {
+        let mut gains = vec![captured];
+        loop {
+            let attackers = self.attackers_to(sq, occupied);
+            let Some((from, piece)) = self.least_valuable(attackers & self.color_combined(side)) else {
+                break;
+            };
+            // A king cannot recapture onto a square the other side still defends.
+            if piece == Piece::King && attackers & self.color_combined(!side) & occupied != EMPTY {
+                break;
+            }
+
+            gains.push(value(on_square) - gains[gains.len() - 1]);
+            occupied ^= BitBoard::from_square(from);
+            on_square = piece;
+            side = !side;
+        }
+
+        while gains.len() > 1 {
+            let last = gains.pop().expect("more than one gain");
+            let previous = gains.last_mut().expect("at least one gain");
+            *previous = -(-*previous).max(last);
+        }
+        gains[0]
+    }
+
+    fn least_valuable(&self, attackers: BitBoard) -> Option<(Square, Piece)> {
+        BY_VALUE.into_iter().find_map(|piece| {
+            let candidates = attackers & self.pieces(piece);
+            (candidates != EMPTY).then(|| (candidates.to_square(), piece))
+        })
+    }
+
+    pub(super) fn occupied(&self) -> BitBoard {
+        self.color_combined(Color::White) | self.color_combined(Color::Black)
+    }
+}
+
+fn value(piece: Piece) -> i32 {
+    PIECE_VALUES[piece.to_index()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn see(fen: &str, san: &str) -> i32 {
+        let game = Game::from_fen(fen).expect("Should parse fen");
+        let mv = game.parse_san(san).expect("Move should be legal");
+        game.see(mv)
+    }
+
+    #[test]
+    fn test_see_simple_captures() {
+        // Undefended pawn.
+        assert_eq!(see("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "Rxe5"), 100);
+        // Pawn defended by a pawn: the rook is lost for it.
+        assert_eq!(see("4k3/8/3p4/4p3/8/8/8/4RK2 w - - 0 1", "Rxe5"), 100 - 500);
+        // Equal trade.
+        assert_eq!(see("4k3/8/5n2/3n4/8/4N3/8/4K3 w - - 0 1", "Nxd5"), 0);
+        // A quiet move that leaves the piece hanging.
+        assert_eq!(see("4k3/8/2p5/8/8/8/8/3QK3 w - - 0 1", "Qd5"), -900);
+        assert_eq!(see("4k3/8/8/8/8/8/8/3QK3 w - - 0 1", "Qd5"), 0);
+    }
+
+    #[test]
+    fn test_see_x_rays() {
+        // Doubled rooks: the rook behind recaptures once the front one has gone.
+        assert_eq!(see("3r2k1/8/8/3p4/8/8/3R4/3R2K1 w - - 0 1", "Rxd5"), 100);
+        assert_eq!(see("3r2k1/8/8/3p4/8/8/3R4/6K1 w - - 0 1", "Rxd5"), 100 - 500);
+        // Queen behind bishop on the diagonal and queen behind rook on the file.
+        assert_eq!(see("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", "Nxe5"), 100 - 320);
+        // The queen behind the bishop wins a second pawn, which still does not pay for the bishop.
+        assert_eq!(see("4k3/8/5p2/4p3/8/2B5/1Q6/4K3 w - - 0 1", "Bxe5"), 100 - 330 + 100);
+    }
+
+    #[test]
+    fn test_see_special_moves() {
+        // En passant opens the d-file for the rook behind the captured pawn.
+        assert_eq!(see("3r2k1/8/8/3pP3/8/8/8/K2R4 w - d6 0 1", "exd6"), 100);
+        assert_eq!(see("3r2k1/8/8/3pP3/8/8/8/K7 w - d6 0 1", "exd6"), 0);
+        // Promotion with capture.
+        assert_eq!(see("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "axb8=Q"), 320 + 900 - 100);
+        // The king cannot recapture a defended rook.
+        assert_eq!(see("4k3/3p4/8/1B6/8/8/8/3RK3 w - - 0 1", "Rxd7+"), 100);
+        assert_eq!(see("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "O-O"), 0);
+    }
+
+    #[test]
+    fn test_see_square() {
+        let game = Game::from_fen("4k3/8/3p4/4p3/8/8/4R3/4K3 w - - 0 1").unwrap();
+        assert_eq!(game.see_square(Square::E5), Some(-400));
+        assert_eq!(game.see_square(Square::D6), None, "Nothing attacks d6.");
+        assert_eq!(game.see_square(Square::E2), None, "White's own piece.");
+        assert!(game.see_ge(game.parse_san("Rxe5").unwrap(), -400));
+        assert!(!game.see_ge(game.parse_san("Rxe5").unwrap(), 0));
+    }
+}
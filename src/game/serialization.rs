@@ -0,0 +1,144 @@
+//! Serde support, behind the `serde` feature. A game is stored as its starting position and
+//! the moves played, in UCI notation, and rebuilt by replaying them on load. That restores
+//! the move history and repetition counts exactly and rejects games with illegal moves.
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use super::{DrawRules, Game, GameOutcome, Move, Variant};
+
+/// The serialized form of a `Game`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Game")]
+struct GameData {
+    variant: Variant,
+    chess960: bool,
+    /// The position before the first move, as FEN.
+    start_fen: String,
+    moves: Vec<Move>,
+    draw_rules: DrawRules,
+    /// A resignation, timeout, agreed draw or adjudication.
+    declared: Option<GameOutcome>,
+}
+
+impl Serialize for Game {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let start = self.replay().next().expect("replay starts with the starting position");
+        GameData {
+            variant: self.variant,
+            chess960: self.is_chess960(),
+            start_fen: start.to_fen(),
+            moves: self.history.iter().map(|record| record.mv).collect(),
+            draw_rules: self.draw_rules,
+            declared: self.declared,
+        }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Game {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = GameData::deserialize(deserializer)?;
+        let mut game = Game::from_fen_variant(&data.start_fen, data.variant).map_err(D::Error::custom)?;
+        game.set_chess960(data.chess960).map_err(D::Error::custom)?;
+        game.set_draw_rules(data.draw_rules);
+        for mv in data.moves {
+            game.play_move(mv).map_err(D::Error::custom)?;
+        }
+        game.declared = data.declared;
+        Ok(game)
+    }
+}
+
+/// Moves are written in UCI notation, like `Display` and `FromStr`.
+impl Serialize for Move {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Move {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let uci = String::deserialize(deserializer)?;
+        uci.parse().map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameResult, Player, Termination};
+    use crate::mcts::ModelOutput;
+
+    fn round_trip(game: &Game) -> Game {
+        let json = serde_json::to_string(game).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn test_game_round_trip() {
+        let mut game = Game::new();
+        for uci in ["g1f3", "g8f6", "f3g1", "f6g8", "e2e4"] {
+            game.make_move(uci).unwrap();
+        }
+        let json = serde_json::to_value(&game).unwrap();
+        assert_eq!(json["start_fen"], "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(json["moves"], serde_json::json!(["g1f3", "g8f6", "f3g1", "f6g8", "e2e4"]));
+
+        let loaded: Game = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.to_fen(), game.to_fen());
+        assert_eq!(loaded.history(), game.history());
+        assert_eq!(loaded.history()[3].repetitions, 2, "Repetition counts are rebuilt.");
+
+        // Carry on to a threefold repetition after loading.
+        let mut game = round_trip(&game);
+        game.undo_move();
+        for uci in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+            game.make_move(uci).unwrap();
+        }
+        assert!(game.is_threefold_repetition());
+    }
+
+    #[test]
+    fn test_game_round_trip_keeps_setup() {
+        let mut game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 7 30").unwrap();
+        game.set_draw_rules(DrawRules::Claimable);
+        game.make_move("e8g8").unwrap();
+        game.resign(Player::White);
+        let loaded = round_trip(&game);
+        assert_eq!(loaded.to_fen(), game.to_fen());
+        assert_eq!(loaded.draw_rules(), DrawRules::Claimable);
+        assert_eq!(loaded.outcome().map(|outcome| outcome.termination), Some(Termination::Resignation));
+
+        let mut chess960 = Game::chess960(518).unwrap();
+        chess960.make_move("e2e4").unwrap();
+        assert!(round_trip(&chess960).is_chess960());
+
+        let mut crazyhouse = Game::with_variant(Variant::Crazyhouse);
+        for uci in ["e2e4", "d7d5", "e4d5", "d8d5", "b1c3", "d5a5", "P@d7"] {
+            crazyhouse.make_move(uci).unwrap();
+        }
+        let loaded = round_trip(&crazyhouse);
+        assert_eq!(loaded.variant(), Variant::Crazyhouse);
+        assert_eq!(loaded.to_fen(), crazyhouse.to_fen());
+    }
+
+    #[test]
+    fn test_invalid_games_are_rejected() {
+        let json = r#"{"variant":"Standard","chess960":false,"start_fen":"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1","moves":["e2e5"],"draw_rules":"Immediate","declared":null}"#;
+        let error = serde_json::from_str::<Game>(json).err().unwrap();
+        assert!(error.to_string().contains("Illegal move: e2e5"), "{}", error);
+        assert!(serde_json::from_str::<Move>(r#""e2""#).is_err());
+    }
+
+    #[test]
+    fn test_result_and_model_output() {
+        assert_eq!(serde_json::to_string(&GameResult::WhiteWin).unwrap(), r#""WhiteWin""#);
+        let result: GameResult = serde_json::from_str(r#""Draw""#).unwrap();
+        assert_eq!(result, GameResult::Draw);
+
+        let output = ModelOutput { value: 0.25, policy: vec![0.5, 0.5] };
+        let json = serde_json::to_string(&output).unwrap();
+        assert_eq!(json, r#"{"value":0.25,"policy":[0.5,0.5]}"#);
+        let loaded: ModelOutput = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.policy, output.policy);
+    }
+}
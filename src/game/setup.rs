@@ -0,0 +1,321 @@
+//! Setting up standard chess positions square by square, as a board editor or a random
+//! position generator does, with diagnostics for setups that cannot arise in a game.
+
+use std::fmt;
+use chess::{BitBoard, CastleRights, Color, File, Piece, Rank, Square, ALL_PIECES, ALL_SQUARES, EMPTY};
+use crate::error::Error;
+use super::chess960::is_attacked_by;
+use super::{Game, Player};
+
+/// A position being edited. Nothing is checked until `diagnostics` or `build`, so a position
+/// may pass through illegal states while it is set up.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PositionBuilder {
+    squares: [Option<(Piece, Player)>; 64],
+    side_to_move: Player,
+    castling: [CastleRights; 2],
+    /// The square a pawn passed over, as in FEN.
+    en_passant: Option<Square>,
+}
+
+/// Something wrong with a position set up in a `PositionBuilder`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SetupIssue {
+    MissingKing(Player),
+    TooManyKings(Player),
+    PawnOnBackRank(Square),
+    /// The side that just moved has left its king in check.
+    OpponentInCheck,
+    /// Castling rights on one side, `KingSide` or `QueenSide`, without the king and rook at home.
+    ImpossibleCastling(Player, CastleRights),
+    /// An en passant square no pawn can just have passed over.
+    InvalidEnPassant(Square),
+}
+
+impl fmt::Display for SetupIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SetupIssue::MissingKing(player) => write!(f, "{:?} has no king", player),
+            SetupIssue::TooManyKings(player) => write!(f, "{:?} has more than one king", player),
+            SetupIssue::PawnOnBackRank(square) => write!(f, "pawn on the back rank at {}", square),
+            SetupIssue::OpponentInCheck => write!(f, "the side not to move is in check"),
+            SetupIssue::ImpossibleCastling(player, side) => {
+                let side = if *side == CastleRights::KingSide { "kingside" } else { "queenside" };
+                write!(f, "{:?} cannot castle {}", player, side)
+            }
+            SetupIssue::InvalidEnPassant(square) => write!(f, "no pawn can have passed over {}", square),
+        }
+    }
+}
+
+impl Default for PositionBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PositionBuilder {
+    /// An empty board with White to move and no castling rights.
+    pub fn new() -> Self {
+        PositionBuilder {
+            squares: [None; 64],
+            side_to_move: Player::White,
+            castling: [CastleRights::NoRights; 2],
+            en_passant: None,
+        }
+    }
+
+    pub fn piece_on(&self, square: Square) -> Option<(Piece, Player)> {
+        self.squares[square.to_index()]
+    }
+
+    /// Puts a piece on `square`, replacing whatever was there.
+    pub fn place_piece(&mut self, square: Square, piece: Piece, player: Player) -> &mut Self {
+        self.squares[square.to_index()] = Some((piece, player));
+        self
+    }
+
+    /// Empties `square`, returning the piece that stood there.
+    pub fn remove_piece(&mut self, square: Square) -> Option<(Piece, Player)> {
+        self.squares[square.to_index()].take()
+    }
+
+    pub fn side_to_move(&self) -> Player {
+        self.side_to_move
+    }
+
+    pub fn set_side_to_move(&mut self, player: Player) -> &mut Self {
+        self.side_to_move = player;
+        self
+    }
+
+    pub fn castling_rights(&self, player: Player) -> CastleRights {
+        self.castling[player as usize]
+    }
+
+    pub fn set_castling_rights(&mut self, player: Player, rights: CastleRights) -> &mut Self {
+        self.castling[player as usize] = rights;
+        self
+    }
+
+    pub fn en_passant(&self) -> Option<Square> {
+        self.en_passant
+    }
+
+    /// Sets the square a pawn has just passed over, e.g. e3 after 1. e4.
+    pub fn set_en_passant(&mut self, square: Option<Square>) -> &mut Self {
+        self.en_passant = square;
+        self
+    }
+
+    /// Everything that keeps the position from being played, empty for a legal setup.
+    pub fn diagnostics(&self) -> Vec<SetupIssue> {
+        let mut issues = Vec::new();
+        for player in [Player::White, Player::Black] {
+            match self.bitboard(Piece::King, player).popcnt() {
+                0 => issues.push(SetupIssue::MissingKing(player)),
+                1 => {}
+                _ => issues.push(SetupIssue::TooManyKings(player)),
+            }
+        }
+
+        issues.extend(ALL_SQUARES.iter()
+            .filter(|sq| matches!(sq.get_rank(), Rank::First | Rank::Eighth))
+            .filter(|&&sq| matches!(self.piece_on(sq), Some((Piece::Pawn, _))))
+            .map(|&sq| SetupIssue::PawnOnBackRank(sq)));
+
+        let waiting = self.side_to_move.opponent();
+        if self.bitboard(Piece::King, waiting).any(|king| self.is_attacked(king, self.side_to_move)) {
+            issues.push(SetupIssue::OpponentInCheck);
+        }
+
+        for player in [Player::White, Player::Black] {
+            let rights = self.castling_rights(player);
+            let back_rank = if player == Player::White { Rank::First } else { Rank::Eighth };
+            let at_home = |file, piece| self.piece_on(Square::make_square(back_rank, file)) == Some((piece, player));
+            let king_home = at_home(File::E, Piece::King);
+            if rights.has_kingside() && !(king_home && at_home(File::H, Piece::Rook)) {
+                issues.push(SetupIssue::ImpossibleCastling(player, CastleRights::KingSide));
+            }
+            if rights.has_queenside() && !(king_home && at_home(File::A, Piece::Rook)) {
+                issues.push(SetupIssue::ImpossibleCastling(player, CastleRights::QueenSide));
+            }
+        }
+
+        if let Some(square) = self.en_passant.filter(|&sq| !self.is_valid_en_passant(sq)) {
+            issues.push(SetupIssue::InvalidEnPassant(square));
+        }
+        issues
+    }
+
+    /// The position as FEN, whether or not it is legal.
+    pub fn to_fen(&self) -> String {
+        let mut placement = String::new();
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                match self.squares[rank * 8 + file] {
+                    Some((piece, player)) => {
+                        if empty > 0 {
+                            placement.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        placement.push_str(&piece.to_string(player.into()));
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                placement.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                placement.push('/');
+            }
+        }
+
+        let side = if self.side_to_move == Player::White { "w" } else { "b" };
+        let castling = format!("{}{}", self.castling[0].to_string(Color::White), self.castling[1].to_string(Color::Black));
+        let castling = if castling.is_empty() { "-".to_string() } else { castling };
+        let en_passant = self.en_passant.map_or("-".to_string(), |sq| sq.to_string());
+        format!("{} {} {} {} 0 1", placement, side, castling, en_passant)
+    }
+
+    /// The game starting from this position, or every issue with it.
+    pub fn build(&self) -> Result<Game, Error> {
+        let issues = self.diagnostics();
+        if !issues.is_empty() {
+            return Err(Error::InvalidPosition(issues));
+        }
+        Game::from_fen(&self.to_fen())
+    }
+
+    fn bitboard(&self, piece: Piece, player: Player) -> BitBoard {
+        ALL_SQUARES.iter()
+            .filter(|&&sq| self.piece_on(sq) == Some((piece, player)))
+            .fold(EMPTY, |bb, &sq| bb | BitBoard::from_square(sq))
+    }
+
+    fn is_attacked(&self, square: Square, by: Player) -> bool {
+        let occupied = ALL_SQUARES.iter()
+            .filter(|&&sq| self.piece_on(sq).is_some())
+            .fold(EMPTY, |bb, &sq| bb | BitBoard::from_square(sq));
+        let pieces = ALL_PIECES.map(|piece| self.bitboard(piece, by));
+        is_attacked_by(|piece| pieces[piece.to_index()], square, by.into(), occupied)
+    }
+
+    /// Whether an enemy pawn can have just passed over `square` with a double step.
+    fn is_valid_en_passant(&self, square: Square) -> bool {
+        let mover: Color = self.side_to_move.into();
+        let target_rank = if mover == Color::White { Rank::Sixth } else { Rank::Third };
+        let (Some(pawn), Some(start)) = (square.backward(mover), square.forward(mover)) else {
+            return false;
+        };
+        square.get_rank() == target_rank
+            && self.piece_on(pawn) == Some((Piece::Pawn, self.side_to_move.opponent()))
+            && self.piece_on(square).is_none()
+            && self.piece_on(start).is_none()
+    }
+}
+
+impl Game {
+    /// The current position in a `PositionBuilder`, for editing. Only standard chess is
+    /// kept: Chess960 castling rights, pockets and check counts are dropped, and the
+    /// move counters start again.
+    pub fn to_builder(&self) -> PositionBuilder {
+        let mut builder = PositionBuilder::new();
+        for &square in ALL_SQUARES.iter() {
+            if let (Some(piece), Some(color)) = (self.piece_on(square), self.color_on(square)) {
+                builder.place_piece(square, piece, color.into());
+            }
+        }
+        builder.set_side_to_move(self.current_player());
+        if !self.is_chess960() {
+            for player in [Player::White, Player::Black] {
+                builder.set_castling_rights(player, self.castle_rights(player.into()));
+            }
+        }
+        builder.set_en_passant(self.en_passant_target());
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kings() -> PositionBuilder {
+        let mut builder = PositionBuilder::new();
+        builder.place_piece(Square::E1, Piece::King, Player::White)
+            .place_piece(Square::E8, Piece::King, Player::Black);
+        builder
+    }
+
+    #[test]
+    fn test_build_position() {
+        let mut builder = kings();
+        builder.place_piece(Square::H1, Piece::Rook, Player::White)
+            .place_piece(Square::D5, Piece::Pawn, Player::White)
+            .place_piece(Square::E5, Piece::Pawn, Player::Black)
+            .set_castling_rights(Player::White, CastleRights::KingSide)
+            .set_en_passant(Some(Square::E6));
+        assert!(builder.diagnostics().is_empty());
+        let mut game = builder.build().unwrap();
+        assert_eq!(game.to_fen(), "4k3/8/8/3Pp3/8/8/8/4K2R w K e6 0 1");
+        game.make_move("d5e6").unwrap();
+        game.make_move("e8d8").unwrap();
+        game.make_move("e1g1").unwrap();
+
+        assert_eq!(builder.remove_piece(Square::D5), Some((Piece::Pawn, Player::White)));
+        assert_eq!(builder.piece_on(Square::D5), None);
+    }
+
+    #[test]
+    fn test_round_trip_through_game() {
+        let mut game = Game::new();
+        game.make_move("e2e4").unwrap();
+        game.make_move("g8f6").unwrap();
+        game.make_move("e4e5").unwrap();
+        game.make_move("d7d5").unwrap();
+        let builder = game.to_builder();
+        assert_eq!(builder.en_passant(), Some(Square::D6));
+        assert_eq!(builder.castling_rights(Player::Black), CastleRights::Both);
+        assert_eq!(builder.build().unwrap().to_fen(), "rnbqkb1r/ppp1pppp/5n2/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 1");
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let empty = PositionBuilder::new();
+        assert_eq!(empty.diagnostics(), [SetupIssue::MissingKing(Player::White), SetupIssue::MissingKing(Player::Black)]);
+
+        let mut builder = kings();
+        builder.place_piece(Square::A1, Piece::King, Player::White)
+            .place_piece(Square::C8, Piece::Pawn, Player::White)
+            .place_piece(Square::E4, Piece::Rook, Player::Black)
+            .set_castling_rights(Player::White, CastleRights::Both)
+            .set_castling_rights(Player::Black, CastleRights::QueenSide)
+            .set_en_passant(Some(Square::E3));
+        assert_eq!(builder.diagnostics(), [
+            SetupIssue::TooManyKings(Player::White),
+            SetupIssue::PawnOnBackRank(Square::C8),
+            SetupIssue::ImpossibleCastling(Player::White, CastleRights::KingSide),
+            SetupIssue::ImpossibleCastling(Player::White, CastleRights::QueenSide),
+            SetupIssue::ImpossibleCastling(Player::Black, CastleRights::QueenSide),
+            SetupIssue::InvalidEnPassant(Square::E3),
+        ]);
+
+        // The rook gives check, which is only illegal when it is Black's move.
+        let mut builder = kings();
+        builder.place_piece(Square::E4, Piece::Rook, Player::Black);
+        assert!(builder.diagnostics().is_empty());
+        builder.set_side_to_move(Player::Black);
+        assert_eq!(builder.diagnostics(), [SetupIssue::OpponentInCheck]);
+        let error = builder.build().unwrap_err();
+        assert!(matches!(&error, Error::InvalidPosition(issues) if issues == &[SetupIssue::OpponentInCheck]));
+        assert_eq!(error.to_string(), "Invalid position: the side not to move is in check");
+
+        let mut builder = kings();
+        builder.set_side_to_move(Player::Black)
+            .place_piece(Square::D2, Piece::Pawn, Player::Black);
+        assert_eq!(builder.diagnostics(), [SetupIssue::OpponentInCheck], "A pawn gives check too.");
+    }
+}
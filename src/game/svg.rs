@@ -0,0 +1,249 @@
+//! SVG images of a position, with arrows, highlighted squares and a heatmap overlay for
+//! showing things like the search's best move or the policy output.
+
+use std::fmt::Write;
+use chess::{Color, File, Piece, Rank, Square};
+use super::{Game, Move};
+
+const LIGHT_SQUARE: &str = "#f0d9b5";
+const DARK_SQUARE: &str = "#b58863";
+
+/// An arrow from one square to another, e.g. a candidate move.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Arrow {
+    pub from: Square,
+    pub to: Square,
+    /// Any SVG colour.
+    pub color: String,
+}
+
+impl Arrow {
+    /// A green arrow, the usual colour for a suggested move.
+    pub fn new(from: Square, to: Square) -> Self {
+        Arrow { from, to, color: "#15781b".to_string() }
+    }
+}
+
+/// Drops are drawn as an arrow pointing at their own square, which shows as just the head.
+impl From<Move> for Arrow {
+    fn from(mv: Move) -> Self {
+        Arrow::new(mv.source(), mv.to())
+    }
+}
+
+/// What `Game::to_svg` draws on top of the board.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvgOptions {
+    /// Width of one square in pixels; the board is eight squares wide, with no margin.
+    pub square_size: u32,
+    /// Black at the bottom.
+    pub flipped: bool,
+    /// File letters and rank numbers in the corners of the edge squares.
+    pub coordinates: bool,
+    pub arrows: Vec<Arrow>,
+    /// Squares to tint, each with an SVG colour.
+    pub highlights: Vec<(Square, String)>,
+    /// A value per square, indexed by `Square::to_index` (a1 = 0, h8 = 63), drawn as a red
+    /// tint of that strength. Values are clamped to `0.0..=1.0`, so normalise them first.
+    pub heatmap: Option<[f32; 64]>,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions {
+            square_size: 45,
+            flipped: false,
+            coordinates: true,
+            arrows: Vec::new(),
+            highlights: Vec::new(),
+            heatmap: None,
+        }
+    }
+}
+
+impl Game {
+    /// Draws the position as a standalone SVG document.
+    pub fn to_svg(&self, options: &SvgOptions) -> String {
+        let size = options.square_size as f32;
+        let board = size * 8.0;
+        let corner = |sq: Square| {
+            let (file, rank) = (sq.get_file().to_index() as f32, sq.get_rank().to_index() as f32);
+            if options.flipped { ((7.0 - file) * size, rank * size) } else { (file * size, (7.0 - rank) * size) }
+        };
+
+        let mut svg = String::new();
+        let _ = writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {board} {board}" width="{board}" height="{board}">"#);
+
+        for sq in chess::ALL_SQUARES {
+            let (x, y) = corner(sq);
+            let light = (sq.get_file().to_index() + sq.get_rank().to_index()) % 2 == 1;
+            let fill = if light { LIGHT_SQUARE } else { DARK_SQUARE };
+            let _ = writeln!(svg, r#"<rect class="square" x="{x}" y="{y}" width="{size}" height="{size}" fill="{fill}"/>"#);
+        }
+
+        if let Some(heatmap) = &options.heatmap {
+            for sq in chess::ALL_SQUARES {
+                let value = heatmap[sq.to_index()].clamp(0.0, 1.0);
+                if value > 0.0 {
+                    let (x, y) = corner(sq);
+                    let _ = writeln!(svg, r##"<rect class="heat" x="{x}" y="{y}" width="{size}" height="{size}" fill="#e0301e" fill-opacity="{value:.3}"/>"##);
+                }
+            }
+        }
+
+        for (sq, color) in &options.highlights {
+            let (x, y) = corner(*sq);
+            let color = escape_attribute(color);
+            let _ = writeln!(svg, r#"<rect class="highlight" x="{x}" y="{y}" width="{size}" height="{size}" fill="{color}" fill-opacity="0.5"/>"#);
+        }
+
+        if options.coordinates {
+            let font = size * 0.22;
+            for i in 0..8 {
+                let file = File::from_index(i);
+                let rank = Rank::from_index(i);
+                let (x, _) = corner(Square::make_square(Rank::First, file));
+                let (_, y) = corner(Square::make_square(rank, File::A));
+                let (fx, fy) = (x + size - font * 0.8, board - font * 0.3);
+                let (rx, ry) = (font * 0.3, y + font);
+                let _ = writeln!(svg, r#"<text class="coordinate" x="{fx}" y="{fy}" font-size="{font}" font-family="sans-serif">{}</text>"#, (b'a' + i as u8) as char);
+                let _ = writeln!(svg, r#"<text class="coordinate" x="{rx}" y="{ry}" font-size="{font}" font-family="sans-serif">{}</text>"#, i + 1);
+            }
+        }
+
+        for sq in chess::ALL_SQUARES {
+            if let (Some(piece), Some(color)) = (self.piece_on(sq), self.color_on(sq)) {
+                let (x, y) = corner(sq);
+                let (cx, cy) = (x + size / 2.0, y + size * 0.82);
+                let (fill, stroke) = if color == Color::White { ("#ffffff", "#000000") } else { ("#000000", "#ffffff") };
+                let _ = writeln!(
+                    svg,
+                    r#"<text class="piece" x="{cx}" y="{cy}" font-size="{}" text-anchor="middle" fill="{fill}" stroke="{stroke}" stroke-width="{}">{}</text>"#,
+                    size * 0.85,
+                    size * 0.02,
+                    piece_glyph(piece),
+                );
+            }
+        }
+
+        for arrow in &options.arrows {
+            let centre = |sq| {
+                let (x, y) = corner(sq);
+                (x + size / 2.0, y + size / 2.0)
+            };
+            svg.push_str(&arrow_svg(centre(arrow.from), centre(arrow.to), size, &arrow.color));
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+/// The filled chess glyphs, coloured by `fill` so both sides share one shape.
+fn piece_glyph(piece: Piece) -> char {
+    match piece {
+        Piece::Pawn => '♟',
+        Piece::Knight => '♞',
+        Piece::Bishop => '♝',
+        Piece::Rook => '♜',
+        Piece::Queen => '♛',
+        Piece::King => '♚',
+    }
+}
+
+/// A shaft and a triangular head, stopping the head at the target square's centre.
+fn arrow_svg((x1, y1): (f32, f32), (x2, y2): (f32, f32), size: f32, color: &str) -> String {
+    let color = escape_attribute(color);
+    let (dx, dy) = (x2 - x1, y2 - y1);
+    let length = (dx * dx + dy * dy).sqrt();
+    let (ux, uy) = if length > 0.0 { (dx / length, dy / length) } else { (0.0, -1.0) };
+    let head = size * 0.45;
+    let half_width = size * 0.25;
+    let (bx, by) = (x2 - ux * head, y2 - uy * head);
+    let (px, py) = (-uy * half_width, ux * half_width);
+
+    let mut svg = String::new();
+    if length > head {
+        let _ = writeln!(
+            svg,
+            r#"<line class="arrow" x1="{x1}" y1="{y1}" x2="{bx}" y2="{by}" stroke="{color}" stroke-width="{}" stroke-linecap="round" opacity="0.8"/>"#,
+            size * 0.18,
+        );
+    }
+    let _ = writeln!(
+        svg,
+        r#"<polygon class="arrow" points="{x2},{y2} {},{} {},{}" fill="{color}" opacity="0.8"/>"#,
+        bx + px, by + py, bx - px, by - py,
+    );
+    svg
+}
+
+/// `value` made safe to put between double quotes in an attribute.
+fn escape_attribute(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_svg_board() {
+        let svg = Game::new().to_svg(&SvgOptions::default());
+        assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 360 360""#));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches(r#"class="square""#).count(), 64);
+        assert_eq!(svg.matches(r#"class="piece""#).count(), 32);
+        assert_eq!(svg.matches(r#"class="coordinate""#).count(), 16);
+        // a1 is dark and in the bottom-left corner.
+        assert!(svg.contains(r##"<rect class="square" x="0" y="315" width="45" height="45" fill="#b58863"/>"##));
+
+        let flipped = Game::new().to_svg(&SvgOptions { flipped: true, coordinates: false, ..SvgOptions::default() });
+        assert!(flipped.contains(r##"<rect class="square" x="315" y="0" width="45" height="45" fill="#b58863"/>"##));
+        assert!(!flipped.contains("coordinate"));
+    }
+
+    #[test]
+    fn test_svg_overlays() {
+        let mut heatmap = [0.0; 64];
+        heatmap[Square::E4.to_index()] = 0.5;
+        heatmap[Square::D4.to_index()] = 2.0;
+        let options = SvgOptions {
+            arrows: vec!["e2e4".parse::<Move>().unwrap().into()],
+            highlights: vec![(Square::E2, "yellow".to_string())],
+            heatmap: Some(heatmap),
+            ..SvgOptions::default()
+        };
+        let svg = Game::new().to_svg(&options);
+
+        assert_eq!(svg.matches(r#"class="heat""#).count(), 2);
+        assert!(svg.contains(r##"x="180" y="180" width="45" height="45" fill="#e0301e" fill-opacity="0.500""##));
+        assert!(svg.contains(r#"fill-opacity="1.000""#), "Heatmap values are clamped.");
+        assert!(svg.contains(r#"<rect class="highlight" x="180" y="270" width="45" height="45" fill="yellow""#));
+        assert_eq!(svg.matches(r#"<line class="arrow""#).count(), 1);
+        assert_eq!(svg.matches(r#"<polygon class="arrow" points="202.5,202.5 "#).count(), 1, "The head points at e4.");
+
+        let mut arrow: Arrow = "e2e4".parse::<Move>().unwrap().into();
+        arrow.color = r#"red"/><script>"#.to_string();
+        let options = SvgOptions {
+            arrows: vec![arrow],
+            highlights: vec![(Square::E2, "a&b".to_string())],
+            ..SvgOptions::default()
+        };
+        let svg = Game::new().to_svg(&options);
+        assert!(!svg.contains("<script>"));
+        assert!(svg.contains(r#"fill="red&quot;/&gt;&lt;script&gt;""#));
+        assert!(svg.contains(r#"fill="a&amp;b""#));
+    }
+}
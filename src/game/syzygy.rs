@@ -0,0 +1,229 @@
+//! Syzygy endgame tablebases: exact win/draw/loss (WDL) and distance-to-zeroing (DTZ)
+//! values for standard chess positions with few enough pieces, read from the `.rtbw` and
+//! `.rtbz` files in a directory. Probing is done by `shakmaty-syzygy`; positions are handed
+//! over as FEN.
+
+use std::fmt;
+use std::path::Path;
+use shakmaty::fen::Fen;
+use shakmaty::{CastlingMode, Chess};
+use shakmaty_syzygy::AmbiguousWdl;
+use crate::error::Error;
+use super::{winner, Game, GameResult, Variant};
+
+/// The tables found in one or more directories. Files are only opened when first probed.
+pub struct Tablebase {
+    tables: shakmaty_syzygy::Tablebase<Chess>,
+}
+
+/// A position's value for the side to move, given its halfmove clock. Cursed wins and blessed
+/// losses are drawn by the fifty-move rule. `MaybeWin` and `MaybeLoss` are a win or loss that
+/// the fifty-move rule may or may not draw: the tables store some distances rounded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss,
+    MaybeLoss,
+    BlessedLoss,
+    Draw,
+    CursedWin,
+    MaybeWin,
+    Win,
+}
+
+impl Wdl {
+    /// 1.0 for a win, -1.0 for a loss and 0.0 for anything the fifty-move rule draws, from the
+    /// side to move's point of view. The uncertain `MaybeWin` and `MaybeLoss` count half.
+    pub fn value(self) -> f64 {
+        match self {
+            Wdl::Win => 1.0,
+            Wdl::MaybeWin => 0.5,
+            Wdl::Loss => -1.0,
+            Wdl::MaybeLoss => -0.5,
+            Wdl::CursedWin | Wdl::Draw | Wdl::BlessedLoss => 0.0,
+        }
+    }
+
+    /// Whether the value is certain, i.e. neither `MaybeWin` nor `MaybeLoss`.
+    pub fn is_exact(self) -> bool {
+        !matches!(self, Wdl::MaybeWin | Wdl::MaybeLoss)
+    }
+}
+
+impl Tablebase {
+    /// Adds every table file in `dir`. Fails if the directory cannot be read or holds no tables.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, Error> {
+        let mut tablebase = Tablebase { tables: shakmaty_syzygy::Tablebase::new() };
+        tablebase.add_directory(dir)?;
+        Ok(tablebase)
+    }
+
+    /// Adds the tables in another directory, e.g. when 3-4-5 and 6-piece tables are kept apart.
+    pub fn add_directory(&mut self, dir: impl AsRef<Path>) -> Result<usize, Error> {
+        let dir = dir.as_ref();
+        let added = self.tables.add_directory(dir)?;
+        if added == 0 {
+            return Err(Error::NoTables(dir.to_path_buf()));
+        }
+        Ok(added)
+    }
+
+    /// The most pieces, kings included, of any table added.
+    pub fn max_pieces(&self) -> usize {
+        self.tables.max_pieces()
+    }
+}
+
+impl fmt::Debug for Tablebase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Tablebase").field("max_pieces", &self.max_pieces()).finish()
+    }
+}
+
+impl Game {
+    /// The position's value for the side to move, if `tablebase` covers it. With the halfmove
+    /// clock at 0 only the WDL table is read; otherwise the DTZ table is needed too, to tell
+    /// whether the fifty-move rule comes first. Fails for variants, positions with castling
+    /// rights or too many pieces, and missing or broken table files.
+    pub fn probe_wdl(&self, tablebase: &Tablebase) -> Result<Wdl, String> {
+        let position = self.syzygy_position(tablebase)?;
+        let wdl = if self.halfmove_clock == 0 {
+            tablebase.tables.probe_wdl_after_zeroing(&position).map(AmbiguousWdl::from)
+        } else {
+            tablebase.tables.probe_wdl(&position)
+        };
+        Ok(match wdl.map_err(|e| e.to_string())? {
+            AmbiguousWdl::Loss => Wdl::Loss,
+            AmbiguousWdl::MaybeLoss => Wdl::MaybeLoss,
+            AmbiguousWdl::BlessedLoss => Wdl::BlessedLoss,
+            AmbiguousWdl::Draw => Wdl::Draw,
+            AmbiguousWdl::CursedWin => Wdl::CursedWin,
+            AmbiguousWdl::MaybeWin => Wdl::MaybeWin,
+            AmbiguousWdl::Win => Wdl::Win,
+        })
+    }
+
+    /// Plies until the next capture or pawn move with best play: positive when the side to
+    /// move wins, negative when it loses, 0 for a draw. The tables store some distances
+    /// rounded, in which case the true distance may be one ply longer.
+    pub fn probe_dtz(&self, tablebase: &Tablebase) -> Result<i32, String> {
+        let position = self.syzygy_position(tablebase)?;
+        let dtz = tablebase.tables.probe_dtz(&position).map_err(|e| e.to_string())?;
+        Ok(dtz.ignore_rounding().0)
+    }
+
+    /// Ends the game early when `tablebase` knows its result, as self-play does to save
+    /// playing out won endgames. Cursed wins and blessed losses are scored as draws; a
+    /// `MaybeWin` or `MaybeLoss` is left to be played out. Returns whether the game was
+    /// adjudicated.
+    pub fn adjudicate_with_tablebase(&mut self, tablebase: &Tablebase) -> bool {
+        if self.is_terminal() {
+            return false;
+        }
+        let Some(wdl) = self.probe_wdl(tablebase).ok().filter(|wdl| wdl.is_exact()) else {
+            return false;
+        };
+        let mover = self.current_player();
+        let result = match wdl {
+            Wdl::Win => winner(mover),
+            Wdl::Loss => winner(mover.opponent()),
+            _ => GameResult::Draw,
+        };
+        self.adjudicate(result);
+        true
+    }
+
+    fn syzygy_position(&self, tablebase: &Tablebase) -> Result<Chess, String> {
+        if self.variant != Variant::Standard {
+            return Err(format!("Syzygy tables do not cover {}", self.variant));
+        }
+        let pieces = self.occupied().popcnt() as usize;
+        if pieces > tablebase.max_pieces() {
+            return Err(format!("{} pieces is more than the tables' {}", pieces, tablebase.max_pieces()));
+        }
+        let mode = if self.is_chess960() { CastlingMode::Chess960 } else { CastlingMode::Standard };
+        let fen: Fen = self.to_fen().parse().map_err(|e| format!("{}", e))?;
+        fen.into_position(mode).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// The KQvK, KRvK and KNvK tables.
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/syzygy");
+
+    /// A directory holding a KQvK "table" of the right size but garbage contents.
+    fn broken_tables(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("chessai_syzygy_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("KQvK.rtbw"), [0u8; 80]).unwrap();
+        fs::write(dir.join("README.txt"), "not a table").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_open_tablebase() {
+        assert!(matches!(Tablebase::open("no_such_directory"), Err(Error::Io(_))));
+        let empty = std::env::temp_dir().join(format!("chessai_syzygy_empty_{}", std::process::id()));
+        fs::create_dir_all(&empty).unwrap();
+        assert!(matches!(Tablebase::open(&empty), Err(Error::NoTables(dir)) if dir == empty));
+
+        let tablebase = Tablebase::open(broken_tables("open")).unwrap();
+        assert_eq!(tablebase.max_pieces(), 3);
+    }
+
+    #[test]
+    fn test_probe_errors() {
+        let tablebase = Tablebase::open(broken_tables("probe")).unwrap();
+        assert!(Game::new().probe_wdl(&tablebase).unwrap_err().contains("more than"));
+        assert!(Game::with_variant(Variant::Antichess).probe_wdl(&tablebase).is_err());
+
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        assert!(game.probe_wdl(&tablebase).is_err(), "The file is not a real table.");
+        assert!(game.probe_dtz(&tablebase).is_err(), "There is no DTZ file.");
+        assert!(!game.adjudicate_with_tablebase(&tablebase));
+        assert!(game.outcome().is_none());
+    }
+
+    #[test]
+    fn test_probe_fixture_tables() {
+        let tablebase = Tablebase::open(FIXTURES).unwrap();
+        assert_eq!(tablebase.max_pieces(), 3);
+
+        let game = Game::from_fen("k7/8/1K6/8/8/8/8/6Q1 w - - 0 1").unwrap();
+        assert_eq!(game.probe_wdl(&tablebase), Ok(Wdl::Win));
+        assert_eq!(game.probe_dtz(&tablebase), Ok(1), "Qg8 is mate.");
+
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/4K2N w - - 0 1").unwrap();
+        assert_eq!(game.probe_wdl(&tablebase), Ok(Wdl::Draw));
+        assert_eq!(game.probe_dtz(&tablebase), Ok(0));
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/4K2N b - - 0 1").unwrap();
+        assert_eq!(game.probe_wdl(&tablebase), Ok(Wdl::Draw));
+
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap();
+        assert_eq!(game.probe_wdl(&tablebase), Ok(Wdl::Loss));
+        assert!(game.probe_dtz(&tablebase).unwrap() < 0);
+        assert!(game.adjudicate_with_tablebase(&tablebase));
+        assert_eq!(game.get_game_result(), Some(GameResult::WhiteWin));
+    }
+
+    #[test]
+    fn test_probe_wdl_counts_halfmove_clock() {
+        let tablebase = Tablebase::open(FIXTURES).unwrap();
+        let fen = |halfmoves: u32| format!("8/8/8/4k3/8/8/8/R3K3 w - - {} 1", halfmoves);
+
+        let game = Game::from_fen(&fen(0)).unwrap();
+        let dtz = game.probe_dtz(&tablebase).unwrap();
+        assert!(dtz > 1, "KRvK takes a while to win.");
+        assert_eq!(game.probe_wdl(&tablebase), Ok(Wdl::Win));
+        assert_eq!(Game::from_fen(&fen(1)).unwrap().probe_wdl(&tablebase), Ok(Wdl::Win));
+
+        // The fifty-move rule comes first.
+        let mut game = Game::from_fen(&fen(101 - dtz as u32)).unwrap();
+        assert_eq!(game.probe_wdl(&tablebase), Ok(Wdl::CursedWin));
+        assert!(game.adjudicate_with_tablebase(&tablebase));
+        assert_eq!(game.get_game_result(), Some(GameResult::Draw));
+    }
+}
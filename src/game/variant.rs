@@ -0,0 +1,348 @@
+//! Rule variants: King of the Hill, Three-check and Racing Kings change how a game is won,
+//! Crazyhouse adds drops. Moves on the board are still ordinary chess moves, so the chess
+//! crate does that move generation and the variant filters moves, adds its own and adds its
+//! own ways for the game to end. Antichess, where kings can be captured, has its own board
+//! and move generation in `antichess`.
+
+use std::fmt;
+use std::str::FromStr;
+use chess::{Color, Piece, Rank, Square, EMPTY};
+use super::crazyhouse::POCKET_FEATURES;
+use super::{winner, Game, GameOutcome, GameResult, Move, Player, Termination};
+
+/// Extra inputs `Game::encode` appends for non-standard variants: which variant is active,
+/// then the checks given by White and Black. Crazyhouse adds the pocket counts after these.
+const VARIANT_FEATURES: usize = 7;
+
+/// The squares a king must reach in King of the Hill.
+const CENTRE: [Square; 4] = [Square::D4, Square::E4, Square::D5, Square::E5];
+
+/// Which rules a game is played under, chosen when the game is created.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Variant {
+    #[default]
+    Standard,
+    /// Bringing the king to one of the four centre squares also wins.
+    KingOfTheHill,
+    /// Giving check for the third time also wins.
+    ThreeCheck,
+    /// No checks allowed; the first king to reach the eighth rank wins. If White gets there
+    /// first, Black may still draw by reaching it on the very next move.
+    RacingKings,
+    /// Captured pieces change sides and can be dropped back onto the board.
+    Crazyhouse,
+    /// Losing chess: captures are compulsory, the king is an ordinary piece, and losing all
+    /// your pieces (or having no move) wins.
+    Antichess,
+}
+
+impl Variant {
+    /// Length of `Game::encode` for this variant, i.e. the input size of its model.
+    pub fn input_size(self) -> usize {
+        match self {
+            Variant::Standard => 384,
+            Variant::Crazyhouse => 384 + VARIANT_FEATURES + POCKET_FEATURES,
+            _ => 384 + VARIANT_FEATURES,
+        }
+    }
+
+    /// The starting position of a new game.
+    fn starting_fen(self) -> &'static str {
+        match self {
+            Variant::RacingKings => "8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1",
+            Variant::Antichess => "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1",
+            _ => "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        }
+    }
+}
+
+/// The name used in PGN `Variant` tags.
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Variant::Standard => "Standard",
+            Variant::KingOfTheHill => "King of the Hill",
+            Variant::ThreeCheck => "Three-check",
+            Variant::RacingKings => "Racing Kings",
+            Variant::Crazyhouse => "Crazyhouse",
+            Variant::Antichess => "Antichess",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Accepts the PGN tag names and their common spellings, ignoring case, spaces and dashes.
+impl FromStr for Variant {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        let key: String = name.chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '_'))
+            .flat_map(char::to_lowercase)
+            .collect();
+        match key.as_str() {
+            "standard" | "chess" => Ok(Variant::Standard),
+            "kingofthehill" | "koth" => Ok(Variant::KingOfTheHill),
+            "threecheck" | "3check" => Ok(Variant::ThreeCheck),
+            "racingkings" => Ok(Variant::RacingKings),
+            "crazyhouse" | "zh" => Ok(Variant::Crazyhouse),
+            "antichess" | "losingchess" | "giveaway" => Ok(Variant::Antichess),
+            _ => Err(format!("Unknown variant: {}", name)),
+        }
+    }
+}
+
+impl Game {
+    /// A new game of `variant` from its usual starting position.
+    pub fn with_variant(variant: Variant) -> Game {
+        Game::from_fen_variant(variant.starting_fen(), variant).expect("starting positions are valid")
+    }
+
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// How many checks `player` has given so far; Three-check is won on the third.
+    pub fn checks_given(&self, player: Player) -> u32 {
+        self.checks[Color::from(player).to_index()]
+    }
+
+    /// Whether the variant allows `mv`, a move legal in ordinary chess.
+    pub(super) fn variant_allows(&self, mv: Move) -> bool {
+        match self.variant {
+            Variant::RacingKings => *self.board_after(mv).checkers() == EMPTY,
+            _ => true,
+        }
+    }
+
+    /// A win or draw by the variant's own rules, checked before mate and the draw rules.
+    pub(super) fn variant_outcome(&self) -> Option<GameOutcome> {
+        match self.variant {
+            Variant::Standard | Variant::Crazyhouse => None,
+            Variant::KingOfTheHill => [Color::White, Color::Black].into_iter()
+                .find(|&color| CENTRE.contains(&self.board.king_square(color)))
+                .map(|color| GameOutcome::new(winner(color.into()), Termination::KingInTheCentre)),
+            Variant::ThreeCheck => [Player::White, Player::Black].into_iter()
+                .find(|&player| self.checks_given(player) >= 3)
+                .map(|player| GameOutcome::new(winner(player), Termination::ThreeChecks)),
+            Variant::RacingKings => self.racing_kings_outcome(),
+            Variant::Antichess => self.antichess_outcome(),
+        }
+    }
+
+    fn racing_kings_outcome(&self) -> Option<GameOutcome> {
+        let home = |color| self.board.king_square(color).get_rank() == Rank::Eighth;
+        let result = match (home(Color::White), home(Color::Black)) {
+            (true, true) => GameResult::Draw,
+            (false, true) => GameResult::BlackWin,
+            (false, false) => return None,
+            (true, false) => {
+                // Black moves after White and gets one last chance to draw level.
+                let black_can_follow = self.board.side_to_move() == Color::Black
+                    && self.generate_moves().iter().any(|mv| {
+                        self.board.piece_on(mv.source()) == Some(Piece::King)
+                            && mv.to().get_rank() == Rank::Eighth
+                    });
+                if black_can_follow {
+                    return None;
+                }
+                GameResult::WhiteWin
+            }
+        };
+        Some(GameOutcome::new(result, Termination::KingOnEighthRank))
+    }
+
+    /// Whether neither side can still win. Kings alone can still race to the centre or the
+    /// eighth rank, in Three-check a single minor piece can still give checks, in
+    /// Crazyhouse captured material comes back, and Antichess is won by running out of pieces.
+    pub(super) fn variant_insufficient_material(&self) -> bool {
+        match self.variant {
+            Variant::Standard => super::has_insufficient_material(&self.board),
+            Variant::ThreeCheck => *self.board.combined() == *self.board.pieces(Piece::King),
+            Variant::KingOfTheHill | Variant::RacingKings | Variant::Crazyhouse | Variant::Antichess => false,
+        }
+    }
+
+    /// Hash contribution of the check counts, which the board does not know about.
+    pub(super) fn variant_key(&self) -> u64 {
+        match self.variant {
+            Variant::ThreeCheck => {
+                super::splitmix(0x3C ^ ((self.checks[0] as u64) << 8) ^ ((self.checks[1] as u64) << 16))
+            }
+            _ => 0,
+        }
+    }
+
+    /// The inputs `encode` appends after the board for non-standard variants, with the
+    /// check counts and pockets of `first` before the other side's.
+    pub(super) fn variant_features(&self, first: Color) -> Vec<f32> {
+        if self.variant == Variant::Standard {
+            return Vec::new();
+        }
+        let mut features = vec![0.0; VARIANT_FEATURES];
+        match self.variant {
+            Variant::KingOfTheHill => features[0] = 1.0,
+            Variant::ThreeCheck => features[1] = 1.0,
+            Variant::RacingKings => features[2] = 1.0,
+            Variant::Crazyhouse => features[3] = 1.0,
+            Variant::Antichess => features[4] = 1.0,
+            Variant::Standard => {}
+        }
+        features[5] = self.checks[first.to_index()] as f32 / 3.0;
+        features[6] = self.checks[(!first).to_index()] as f32 / 3.0;
+        if self.variant == Variant::Crazyhouse {
+            features.extend(self.pocket_features(first));
+        }
+        features
+    }
+
+    /// The Three-check FEN field giving the checks each side still needs, e.g. `3+3`.
+    pub(super) fn check_counts_fen(&self) -> Option<String> {
+        (self.variant == Variant::ThreeCheck)
+            .then(|| format!("{}+{}", 3u32.saturating_sub(self.checks[0]), 3u32.saturating_sub(self.checks[1])))
+    }
+}
+
+/// Removes the Three-check counters from FEN `fields` and returns the checks given by each
+/// side. Both the lichess form, remaining checks after the en passant square (`3+3`), and
+/// the older form, checks given at the very end (`+0+0`), are accepted.
+pub(super) fn take_check_counts(fields: &mut Vec<&str>) -> Result<[u32; 2], String> {
+    let bad_counts = |field: &str| format!("bad check counts '{}'", field);
+
+    if let Some(i) = fields.iter().position(|f| f.starts_with('+')) {
+        let field = fields.remove(i);
+        let counts: Vec<&str> = field[1..].split('+').collect();
+        return match counts[..] {
+            [white, black] => Ok([
+                white.parse().map_err(|_| bad_counts(field))?,
+                black.parse().map_err(|_| bad_counts(field))?,
+            ]),
+            _ => Err(bad_counts(field)),
+        };
+    }
+
+    if fields.len() > 4 && fields[4].contains('+') {
+        let field = fields.remove(4);
+        let (white, black) = field.split_once('+').ok_or_else(|| bad_counts(field))?;
+        let remaining = |count: &str| count.parse::<u32>().ok().filter(|&n| n <= 3).ok_or_else(|| bad_counts(field));
+        return Ok([3 - remaining(white)?, 3 - remaining(black)?]);
+    }
+
+    Ok([0, 0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Move, PerftRules};
+
+    fn mv(uci: &str) -> Move {
+        uci.parse().expect("Should be valid UCI")
+    }
+
+    #[test]
+    fn test_variant_names() {
+        for variant in [Variant::Standard, Variant::KingOfTheHill, Variant::ThreeCheck, Variant::RacingKings, Variant::Crazyhouse, Variant::Antichess] {
+            assert_eq!(variant.to_string().parse::<Variant>(), Ok(variant));
+        }
+        assert_eq!("kingofthehill".parse::<Variant>(), Ok(Variant::KingOfTheHill));
+        assert_eq!("3check".parse::<Variant>(), Ok(Variant::ThreeCheck));
+        assert!("Bughouse".parse::<Variant>().is_err());
+    }
+
+    #[test]
+    fn test_king_of_the_hill() {
+        let mut game = Game::from_fen_variant("4k3/8/8/8/8/4K3/8/8 w - - 0 1", Variant::KingOfTheHill).unwrap();
+        assert!(!game.is_terminal(), "Bare kings can still race to the centre.");
+
+        game.make_move("e3e4").unwrap();
+        let outcome = game.outcome().expect("The king reached the centre");
+        assert_eq!(outcome.result, GameResult::WhiteWin);
+        assert_eq!(outcome.to_string(), "White wins by king in the centre");
+        assert!(game.legal_moves().is_empty());
+
+        let game = Game::from_fen("4k3/8/8/8/8/4K3/8/8 w - - 0 1").unwrap();
+        assert!(game.is_terminal(), "Standard rules are untouched.");
+    }
+
+    #[test]
+    fn test_three_check() {
+        let mut game = Game::from_fen_variant("4k3/8/8/8/8/8/8/R3K3 w - - 1+3 0 1", Variant::ThreeCheck).unwrap();
+        assert_eq!(game.checks_given(Player::White), 2);
+        assert_eq!(game.to_fen(), "4k3/8/8/8/8/8/8/R3K3 w - - 1+3 0 1");
+        let before = game.get_hash();
+
+        game.make_move("a1a8").unwrap();
+        assert_eq!(game.checks_given(Player::White), 3);
+        assert_eq!(game.to_fen(), "R3k3/8/8/8/8/8/8/4K3 b - - 0+3 1 1");
+        assert_eq!(game.outcome().unwrap().termination, Termination::ThreeChecks);
+        assert_eq!(game.result_value(), 1.0);
+
+        game.undo_move();
+        assert_eq!(game.checks_given(Player::White), 2);
+        assert_eq!(game.get_hash(), before);
+
+        let old_style = Game::from_fen_variant("4k3/8/8/8/8/8/8/R3K3 w - - 0 1 +2+0", Variant::ThreeCheck).unwrap();
+        assert_eq!(old_style.to_fen(), game.to_fen());
+        let fresh = Game::from_fen_variant("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", Variant::ThreeCheck).unwrap();
+        assert_ne!(fresh.get_hash(), game.get_hash(), "Check counts are part of the position.");
+
+        let bare_kings = Game::from_fen_variant("4k3/8/8/8/8/8/8/4K3 w - - 3+3 0 1", Variant::ThreeCheck).unwrap();
+        assert_eq!(bare_kings.outcome().unwrap().termination, Termination::InsufficientMaterial);
+    }
+
+    #[test]
+    fn test_racing_kings() {
+        let game = Game::with_variant(Variant::RacingKings);
+        assert_eq!(game.legal_moves().len(), 21);
+
+        // Giving check is illegal.
+        let mut game = Game::from_fen_variant("8/8/8/8/8/8/k7/6KR w - - 0 1", Variant::RacingKings).unwrap();
+        assert!(!game.legal_moves().contains(&mv("h1h2")));
+        assert!(game.make_move("h1h2").is_err());
+
+        // White arrives first and Black cannot follow.
+        let mut game = Game::from_fen_variant("8/6K1/8/8/8/8/k7/8 w - - 0 1", Variant::RacingKings).unwrap();
+        game.make_move("g7g8").unwrap();
+        assert_eq!(game.get_game_result(), Some(GameResult::WhiteWin));
+
+        // Black can still reach the eighth rank: a draw if it does, a loss if not.
+        let mut game = Game::from_fen_variant("8/1k4K1/8/8/8/8/8/8 w - - 0 1", Variant::RacingKings).unwrap();
+        game.make_move("g7g8").unwrap();
+        assert!(!game.is_terminal());
+        game.make_move("b7b8").unwrap();
+        assert_eq!(game.outcome().unwrap().to_string(), "Draw by king on the eighth rank");
+        game.undo_move();
+        game.make_move("b7a6").unwrap();
+        assert_eq!(game.get_game_result(), Some(GameResult::WhiteWin));
+    }
+
+    #[test]
+    fn test_variant_perft() {
+        let racing = Game::with_variant(Variant::RacingKings);
+        assert_eq!(racing.perft(3, PerftRules::MovesOnly), 11264);
+        assert_eq!(racing.perft(4, PerftRules::MovesOnly), 296242);
+
+        let koth = Game::with_variant(Variant::KingOfTheHill);
+        assert_eq!(koth.perft(3, PerftRules::MovesOnly), 8902);
+
+        let three_check = Game::from_fen_variant(
+            "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/8/PPPP1PPP/RNBQK1NR w KQkq - 2 3 +1+0",
+            Variant::ThreeCheck,
+        ).unwrap();
+        assert_eq!(three_check.perft(3, PerftRules::MovesOnly), 32629);
+    }
+
+    #[test]
+    fn test_variant_encoding() {
+        assert_eq!(Game::new().encode().len(), Variant::Standard.input_size());
+        for variant in [Variant::KingOfTheHill, Variant::ThreeCheck, Variant::RacingKings, Variant::Crazyhouse, Variant::Antichess] {
+            assert_eq!(Game::with_variant(variant).encode().len(), variant.input_size());
+        }
+
+        let game = Game::from_fen_variant("4k3/8/8/8/8/8/8/R3K3 w - - 1+3 0 1", Variant::ThreeCheck).unwrap();
+        let encoded = game.encode();
+        assert_eq!(&encoded[384..], &[0.0, 1.0, 0.0, 0.0, 0.0, 2.0 / 3.0, 0.0]);
+    }
+}
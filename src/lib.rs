@@ -1,6 +1,10 @@
 pub mod game;
 pub mod mcts;
 pub mod chess_ai_model;
+pub mod pgn;
+pub mod epd;
+pub mod book;
+pub mod error;
 
 
 
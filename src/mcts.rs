@@ -1,10 +1,12 @@
 use std::sync::Arc;
-use mcts::{Evaluator, GameState, SearchHandle, MCTS};
-use crate::game::Game;
+use mcts::{Evaluator, GameState, MCTSManager, SearchHandle, MCTS};
+use crate::game::{Encoding, Game, Move, Perspective, Player, Tablebase, Variant};
 use mcts::transposition_table::{ApproxTable, TranspositionHash};
 use mcts::tree_policy::UCTPolicy;
 use tch::Tensor;
 use crate::chess_ai_model::ChessAIModel;
+use crate::book::OpeningBook;
+use crate::error::Error;
 
 #[derive(Clone)]
 pub struct ChessMCTSState {
@@ -24,12 +26,12 @@ impl TranspositionHash for ChessMCTSState {
 }
 
 impl GameState for ChessMCTSState {
-    type Move = String;
-    type Player = String;
+    type Move = Move;
+    type Player = Player;
     type MoveList = Vec<Self::Move>;
 
     fn current_player(&self) -> Self::Player {
-        self.game.current_player().parse().unwrap()
+        self.game.current_player()
     }
 
     fn available_moves(&self) -> Self::MoveList {
@@ -37,26 +39,79 @@ impl GameState for ChessMCTSState {
     }
 
     fn make_move(&mut self, mov: &Self::Move) {
-        self.game.make_move(mov).expect("Move should be legal");
+        self.game.play_move(*mov).expect("Move should be legal");
     }
 }
 
 pub trait ChessModel: Send + Sync {
     fn evaluate(&self, game: &Game) -> ModelOutput;
+
+    /// Whose score `ModelOutput::value` is.
+    fn perspective(&self) -> Perspective {
+        Perspective::White
+    }
+}
+
+/// Lets one loaded model serve several searches, e.g. every position of a test suite.
+impl<M: ChessModel + ?Sized> ChessModel for Arc<M> {
+    fn evaluate(&self, game: &Game) -> ModelOutput {
+        (**self).evaluate(game)
+    }
+
+    fn perspective(&self) -> Perspective {
+        (**self).perspective()
+    }
 }
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ModelOutput {
     pub value: f64,        // Position evaluation (-1 to 1)
     pub policy: Vec<f64>,  // Probabilities for each legal move
 }
 
+/// A state's model output, with the player to move there for models whose values are
+/// from the side to move's point of view.
+#[derive(Clone)]
+pub struct StateEvaluation {
+    pub output: ModelOutput,
+    pub mover: Player,
+}
+
 
 
 pub struct ChessEvaluator {
     model: Box<dyn ChessModel>,  // Your trained model
+    perspective: Perspective,
+    tablebase: Option<Arc<Tablebase>>,
 }
 
 impl ChessEvaluator {
+    pub fn new(model: Box<dyn ChessModel>) -> Self {
+        let perspective = model.perspective();
+        ChessEvaluator { model, perspective, tablebase: None }
+    }
+
+    /// Uses the exact tablebase value instead of the model in positions the tables cover.
+    pub fn with_tablebase(mut self, tablebase: Arc<Tablebase>) -> Self {
+        self.tablebase = Some(tablebase);
+        self
+    }
+
+    /// The tablebase's verdict as a model output, with a uniform policy, if it has one.
+    fn tablebase_output(&self, game: &Game, moves: usize) -> Option<ModelOutput> {
+        let tablebase = self.tablebase.as_ref()?;
+        if game.is_terminal() {
+            return None;
+        }
+        let value = game.probe_wdl(tablebase).ok()?.value();
+        let negate = self.perspective == Perspective::White && game.current_player() == Player::Black;
+        Some(ModelOutput {
+            // The tables' values are from the side to move's point of view, like the model's may be.
+            value: if negate { -value } else { value },
+            policy: vec![1.0 / moves as f64; moves],
+        })
+    }
+
     fn evaluate_state(&self, state: &ChessMCTSState) -> f64 {
         // Use the existing result_value() function for terminal states
         let terminal_value = state.game.result_value();
@@ -97,70 +152,120 @@ impl MCTS for ChessMCTS {
 }
 
 impl Evaluator<ChessMCTS> for ChessEvaluator {
-    type StateEvaluation = ModelOutput;
+    type StateEvaluation = StateEvaluation;
 
     fn evaluate_new_state(
         &self,
         state: &ChessMCTSState,
-        moves: &Vec<String>,
+        moves: &Vec<Move>,
         _: Option<SearchHandle<ChessMCTS>>,
-    ) -> (Vec<()>, ModelOutput) {
-        let model_output = self.model.evaluate(&state.game);
-        (vec![(); moves.len()], model_output)
+    ) -> (Vec<()>, StateEvaluation) {
+        let model_output = self.tablebase_output(&state.game, moves.len())
+            .unwrap_or_else(|| self.model.evaluate(&state.game));
+        (vec![(); moves.len()], StateEvaluation { output: model_output, mover: state.game.current_player() })
     }
 
     fn evaluate_existing_state(
         &self,
         _state: &ChessMCTSState,
-        eval: &ModelOutput,
+        eval: &StateEvaluation,
         handle: SearchHandle<ChessMCTS>,
-    ) -> ModelOutput {
+    ) -> StateEvaluation {
         // Use the existing evaluation
         eval.clone()
     }
 
-    fn interpret_evaluation_for_player(&self, eval: &ModelOutput, player: &String) -> i64 {
-        let value = if player == "White" {
-            eval.value
+    fn interpret_evaluation_for_player(&self, eval: &StateEvaluation, player: &Player) -> i64 {
+        let scored_for = match self.perspective {
+            Perspective::White => Player::White,
+            Perspective::SideToMove => eval.mover,
+        };
+        let value = if *player == scored_for {
+            eval.output.value
         } else {
-            -eval.value  // Negate value for Black
+            -eval.output.value  // Negate value for the other player
         };
         (value * 10000.0) as i64
     }
 }
 
+/// Picks a move to play: from `book` while the position is in it, otherwise by running
+/// `playouts` MCTS playouts evaluated by `model`. `None` if there is no legal move.
+pub fn choose_move(game: &Game, model: Box<dyn ChessModel>, playouts: u64, book: Option<&OpeningBook>) -> Option<Move> {
+    if let Some(mv) = book.and_then(|book| book.choose(game)) {
+        return Some(mv);
+    }
+    let state = ChessMCTSState::new(game.clone());
+    let mut mcts = MCTSManager::new(state, ChessMCTS, ChessEvaluator::new(model), UCTPolicy::new(0.5), ApproxTable::new(1024));
+    mcts.playout_n(playouts);
+    mcts.best_move()
+}
+
 pub struct RealChessModel {
     ai_model: Arc<ChessAIModel>,
+    encoding: Encoding,
+    perspective: Perspective,
 }
 
 impl RealChessModel {
     pub fn new() -> Self {
         RealChessModel {
             ai_model: Arc::new(ChessAIModel::new()),
+            encoding: Encoding::Pieces,
+            perspective: Perspective::White,
         }
     }
-    pub fn from_file(filepath: &str) -> Self {
+    /// A fresh model sized for the encoding of `variant`.
+    pub fn for_variant(variant: Variant) -> Self {
+        Self::with_encoding(variant, Encoding::Pieces)
+    }
+    /// A fresh model for `variant` that reads positions in `encoding`.
+    pub fn with_encoding(variant: Variant, encoding: Encoding) -> Self {
         RealChessModel {
-            ai_model: Arc::new(ChessAIModel::from_file(filepath)),
+            ai_model: Arc::new(ChessAIModel::with_input_size(encoding.input_size(variant) as i64)),
+            encoding,
+            perspective: Perspective::White,
         }
     }
+    /// Encodes positions, and reads the network's value, from `perspective`.
+    pub fn with_perspective(mut self, perspective: Perspective) -> Self {
+        self.perspective = perspective;
+        self
+    }
+    /// Loads weights saved from a model built for `variant`, `encoding` and `perspective`;
+    /// the file does not record them.
+    pub fn from_file(filepath: &str, variant: Variant, encoding: Encoding, perspective: Perspective) -> Result<Self, Error> {
+        Ok(RealChessModel {
+            ai_model: Arc::new(ChessAIModel::from_file(filepath, encoding.input_size(variant) as i64)?),
+            encoding,
+            perspective,
+        })
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
 }
 
 impl ChessModel for RealChessModel {
     fn evaluate(&self, game: &Game) -> ModelOutput {
-        let input_tensor = Tensor::from_slice(&game.encode());
+        let input_tensor = Tensor::from_slice(&game.encode_for(self.encoding, self.perspective));
         let value = self.ai_model.evaluate(&input_tensor);
         // Placeholder for policy vector
         let policy = vec![1.0 / game.legal_moves().len() as f64; game.legal_moves().len()];
         ModelOutput { value, policy }
     }
+
+    fn perspective(&self) -> Perspective {
+        self.perspective
+    }
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use mcts::MCTSManager;
+    use crate::book::tests::entry;
 
     #[test]
     fn test_available_moves() {
@@ -178,7 +283,7 @@ mod tests {
         let mut mcts = MCTSManager::new(
             state,
             ChessMCTS,
-            ChessEvaluator { model: Box::new(MockModel) },
+            ChessEvaluator::new(Box::new(MockModel)),
             UCTPolicy::new(0.5),
             ApproxTable::new(1024),
         );
@@ -201,6 +306,42 @@ mod tests {
         }
     }
 
+    /// Scores every position 0.5, for whichever side its perspective favours.
+    struct ConstantModel(Perspective);
+
+    impl ChessModel for ConstantModel {
+        fn evaluate(&self, game: &Game) -> ModelOutput {
+            ModelOutput { value: 0.5, policy: vec![1.0 / game.legal_moves().len() as f64; game.legal_moves().len()] }
+        }
+
+        fn perspective(&self) -> Perspective {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_value_perspective() {
+        let mut game = Game::new();
+        game.make_move("e2e4").unwrap();
+        let state = ChessMCTSState::new(game);
+        let moves = state.available_moves();
+
+        let evaluator = ChessEvaluator::new(Box::new(ConstantModel(Perspective::White)));
+        let (_, eval) = evaluator.evaluate_new_state(&state, &moves, None);
+        assert_eq!(evaluator.interpret_evaluation_for_player(&eval, &Player::White), 5000);
+        assert_eq!(evaluator.interpret_evaluation_for_player(&eval, &Player::Black), -5000);
+
+        // Black is to move, so a side-to-move model's value is Black's score.
+        let evaluator = ChessEvaluator::new(Box::new(Arc::new(ConstantModel(Perspective::SideToMove))));
+        let (_, eval) = evaluator.evaluate_new_state(&state, &moves, None);
+        assert_eq!(evaluator.interpret_evaluation_for_player(&eval, &Player::Black), 5000);
+        assert_eq!(evaluator.interpret_evaluation_for_player(&eval, &Player::White), -5000);
+
+        let model = RealChessModel::new().with_perspective(Perspective::SideToMove);
+        assert_eq!(model.perspective(), Perspective::SideToMove);
+        assert!(model.evaluate(&state.game).value.abs() <= 1.0);
+    }
+
     #[test]
     fn test_mcts_with_mock_model() {
         let game = Game::new();
@@ -209,7 +350,7 @@ mod tests {
         let mut mcts = MCTSManager::new(
             state,
             ChessMCTS,
-            ChessEvaluator { model: Box::new(MockModel) },
+            ChessEvaluator::new(Box::new(MockModel)),
             UCTPolicy::new(0.5),
             ApproxTable::new(1024),
         );
@@ -233,6 +374,10 @@ mod tests {
 
         assert!(output.value.abs() <= 1.0, "Model evaluation value should be within [-1, 1].");
         assert_eq!(output.policy.len(), game.legal_moves().len(), "Model policy output length should match the number of legal moves.");
+
+        let model = RealChessModel::with_encoding(Variant::Standard, Encoding::AlphaZero);
+        assert_eq!(model.encoding(), Encoding::AlphaZero);
+        assert_eq!(model.evaluate(&game).policy.len(), game.legal_moves().len());
     }
 
     #[test]
@@ -240,7 +385,7 @@ mod tests {
         let game = Game::new();
         let model = RealChessModel::new();
         let state = ChessMCTSState::new(game);
-        let evaluator = ChessEvaluator { model: Box::new(model) };
+        let evaluator = ChessEvaluator::new(Box::new(model));
         let mut mcts = MCTSManager::new(
             state,
             ChessMCTS,
@@ -254,16 +399,70 @@ mod tests {
         assert!(best_move.is_some(), "MCTS should return a best move.");
     }
 
+    #[test]
+    fn test_mcts_plays_variants() {
+        for variant in [Variant::KingOfTheHill, Variant::ThreeCheck, Variant::RacingKings, Variant::Antichess] {
+            let game = Game::with_variant(variant);
+            let state = ChessMCTSState::new(game.clone());
+            let evaluator = ChessEvaluator::new(Box::new(RealChessModel::for_variant(variant)));
+            let mut mcts = MCTSManager::new(state, ChessMCTS, evaluator, UCTPolicy::new(0.5), ApproxTable::new(1024));
+
+            mcts.playout_n(200);
+            let best_move = mcts.best_move().expect("MCTS should return a best move.");
+            assert!(game.legal_moves().contains(&best_move));
+        }
+    }
+
+    #[test]
+    fn test_choose_move_uses_book_first() {
+        let game = Game::new();
+        let book = OpeningBook::from_bytes(&entry(&game, "b1a3", 1)).unwrap();
+        // The book move is played without searching at all.
+        assert_eq!(choose_move(&game, Box::new(MockModel), 0, Some(&book)), Some("b1a3".parse().unwrap()));
+
+        let mut out_of_book = game.clone();
+        out_of_book.make_move("e2e4").unwrap();
+        let mv = choose_move(&out_of_book, Box::new(MockModel), 100, Some(&book)).expect("The search finds a move.");
+        assert!(out_of_book.legal_moves().contains(&mv));
+    }
+
+    #[test]
+    fn test_evaluator_uses_tablebase() {
+        // An unreadable table is ignored and the model answers instead.
+        let broken = std::env::temp_dir().join(format!("chessai_evaluator_tables_{}", std::process::id()));
+        std::fs::create_dir_all(&broken).unwrap();
+        std::fs::write(broken.join("KQvK.rtbw"), [0u8; 80]).unwrap();
+        let evaluator = ChessEvaluator::new(Box::new(MockModel)).with_tablebase(Arc::new(Tablebase::open(&broken).unwrap()));
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        assert!(evaluator.tablebase_output(&game, game.legal_moves().len()).is_none());
+
+        let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/syzygy");
+        let evaluator = ChessEvaluator::new(Box::new(MockModel)).with_tablebase(Arc::new(Tablebase::open(fixtures).unwrap()));
+        // Black to move is lost, so the value is a White win whatever the model says.
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap();
+        let output = evaluator.tablebase_output(&game, game.legal_moves().len()).expect("KQvK is covered");
+        assert_eq!(output.value, 1.0);
+        assert_eq!(output.policy.len(), game.legal_moves().len());
+        let game = Game::from_fen("8/8/8/8/8/8/2k5/3R1K2 b - - 0 1").unwrap();
+        assert_eq!(evaluator.tablebase_output(&game, game.legal_moves().len()).unwrap().value, 0.0);
+
+        // From the side to move's point of view Black's loss stays negative.
+        let evaluator = ChessEvaluator { perspective: Perspective::SideToMove, ..evaluator };
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap();
+        assert_eq!(evaluator.tablebase_output(&game, game.legal_moves().len()).unwrap().value, -1.0);
+        assert!(evaluator.tablebase_output(&Game::new(), 20).is_none());
+    }
+
     #[test]
     fn test_model_save_and_load() {
         let model = RealChessModel::new();
         let filepath = "dummy_model_file";
 
         // Save the model to a file
-        model.ai_model.save_to_file(filepath);
+        model.ai_model.save_to_file(filepath).unwrap();
 
         // Load the model from the file
-        let loaded_model = RealChessModel::from_file(filepath);
+        let loaded_model = RealChessModel::from_file(filepath, Variant::Standard, Encoding::Pieces, Perspective::White).unwrap();
         let game = Game::new();
         let output = loaded_model.evaluate(&game);
 
@@ -271,4 +470,31 @@ mod tests {
         assert_eq!(output.policy.len(), game.legal_moves().len(), "Model policy output length should match the number of legal moves.");
     }
 
+    #[test]
+    fn test_model_load_errors() {
+        let load = |filepath: &str| RealChessModel::from_file(filepath, Variant::Standard, Encoding::Pieces, Perspective::White);
+        assert!(matches!(load("no_such_model_file"), Err(Error::Io(_))));
+
+        let filepath = std::env::temp_dir().join(format!("chessai_corrupt_model_{}", std::process::id()));
+        std::fs::write(&filepath, b"not a checkpoint").unwrap();
+        assert!(matches!(load(filepath.to_str().unwrap()), Err(Error::IncompatibleWeights(_))));
+    }
+
+    #[test]
+    fn test_model_round_trip_keeps_encoding() {
+        let model = RealChessModel::with_encoding(Variant::ThreeCheck, Encoding::AlphaZero).with_perspective(Perspective::SideToMove);
+        let filepath = std::env::temp_dir().join(format!("chessai_alpha_zero_model_{}", std::process::id()));
+        let filepath = filepath.to_str().unwrap();
+        model.ai_model.save_to_file(filepath).unwrap();
+
+        let loaded = RealChessModel::from_file(filepath, Variant::ThreeCheck, Encoding::AlphaZero, Perspective::SideToMove).unwrap();
+        assert_eq!(loaded.encoding(), Encoding::AlphaZero);
+        assert_eq!(loaded.perspective(), Perspective::SideToMove);
+        let mut game = Game::with_variant(Variant::ThreeCheck);
+        game.make_move("e2e4").unwrap();
+        let output = loaded.evaluate(&game);
+        assert_eq!(output.policy.len(), game.legal_moves().len());
+        assert_eq!(ChessEvaluator::new(Box::new(loaded)).perspective, Perspective::SideToMove);
+    }
+
 }
\ No newline at end of file
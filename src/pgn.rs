@@ -0,0 +1,546 @@
+//! Reading and writing games in Portable Game Notation (PGN).
+//!
+//! Import follows the main line of each game: comments, NAGs and recursive variations are
+//! parsed and skipped. Export always writes the Seven Tag Roster followed by any extra tags.
+
+use std::iter::Peekable;
+use std::str::Chars;
+use crate::error::Error;
+use crate::game::{DrawRules, Game, GameResult, Player, Variant};
+
+/// The tags every exported game carries, in the order the PGN standard prescribes.
+pub const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// One game read from a PGN file: its tag pairs and the main line replayed into a `Game`.
+#[derive(Clone, Debug)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub game: Game,
+}
+
+impl PgnGame {
+    /// Value of the first tag named `name`, if present.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    /// The game's result as written in the PGN (`1-0`, `0-1`, `1/2-1/2` or `*`).
+    pub fn result(&self) -> &str {
+        self.tag("Result").unwrap_or("*")
+    }
+}
+
+/// Parses every game in `text`.
+pub fn parse_pgn(text: &str) -> Result<Vec<PgnGame>, Error> {
+    let mut parser = Parser { chars: text.chars().peekable() };
+    let mut games = Vec::new();
+
+    while let Some(game) = parser.next_game(games.len() + 1)? {
+        games.push(game);
+    }
+
+    Ok(games)
+}
+
+/// Writes `game` as a single PGN game. `tags` fill in or override the Seven Tag Roster
+/// (missing roster tags default to `?`) and any other tags are written after it.
+/// `Result` defaults to the game's own result, or `*` while it is still in progress.
+/// Finished games also get a `Termination` tag and a closing comment giving the reason.
+pub fn write_pgn(game: &Game, tags: &[(&str, &str)]) -> String {
+    let outcome = game.outcome();
+    let result = match outcome.map(|o| o.result) {
+        Some(GameResult::WhiteWin) => "1-0",
+        Some(GameResult::BlackWin) => "0-1",
+        Some(GameResult::Draw) => "1/2-1/2",
+        None => "*",
+    };
+    let lookup = |name: &str| tags.iter().find(|(n, _)| *n == name).map(|(_, v)| *v);
+
+    let mut out = String::new();
+    for name in SEVEN_TAG_ROSTER {
+        let default = match name {
+            "Date" => "????.??.??",
+            "Result" => result,
+            _ => "?",
+        };
+        push_tag(&mut out, name, lookup(name).unwrap_or(default));
+    }
+
+    let start = game.replay().next().expect("replay always yields the starting position");
+    let start_fen = start.to_fen();
+    if lookup("Variant").is_none() {
+        if game.variant() != Variant::Standard {
+            push_tag(&mut out, "Variant", &game.variant().to_string());
+        } else if game.is_chess960() {
+            push_tag(&mut out, "Variant", "Chess960");
+        }
+    }
+    let usual_start = Game::with_variant(game.variant()).to_fen();
+    if (start_fen != usual_start || game.is_chess960()) && lookup("FEN").is_none() {
+        push_tag(&mut out, "SetUp", "1");
+        push_tag(&mut out, "FEN", &start_fen);
+    }
+    if let (Some(outcome), None) = (outcome, lookup("Termination")) {
+        push_tag(&mut out, "Termination", outcome.termination.pgn_tag());
+    }
+    for (name, value) in tags {
+        if !SEVEN_TAG_ROSTER.contains(name) {
+            push_tag(&mut out, name, value);
+        }
+    }
+    out.push('\n');
+
+    let mut tokens = Vec::with_capacity(game.history().len() * 3 / 2 + 1);
+    let mut move_number = start.fullmove_number();
+    for (ply, (position, record)) in game.replay().zip(game.history()).enumerate() {
+        let white_to_move = position.current_player() == Player::White;
+        if white_to_move {
+            tokens.push(format!("{}.", move_number));
+        } else if ply == 0 {
+            tokens.push(format!("{}...", move_number));
+        }
+        tokens.push(position.to_san(record.mv).expect("recorded moves are legal"));
+        if !white_to_move {
+            move_number += 1;
+        }
+    }
+    if let Some(outcome) = outcome {
+        tokens.extend(format!("{{{}}}", outcome).split(' ').map(str::to_string));
+    }
+    tokens.push(lookup("Result").unwrap_or(result).to_string());
+
+    // Export format keeps movetext lines under 80 characters.
+    let mut line_len = 0;
+    for token in tokens {
+        if line_len > 0 && line_len + 1 + token.len() > 79 {
+            out.push('\n');
+            line_len = 0;
+        } else if line_len > 0 {
+            out.push(' ');
+            line_len += 1;
+        }
+        line_len += token.len();
+        out.push_str(&token);
+    }
+    out.push('\n');
+
+    out
+}
+
+fn push_tag(out: &mut String, name: &str, value: &str) {
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+    out.push_str(&format!("[{} \"{}\"]\n", name, escaped));
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl Parser<'_> {
+    /// Reads the next game, or `None` once only whitespace is left.
+    fn next_game(&mut self, number: usize) -> Result<Option<PgnGame>, Error> {
+        let mut tags = Vec::new();
+        let mut game: Option<Game> = None;
+        let mut result = None;
+
+        loop {
+            self.skip_whitespace();
+            let Some(&c) = self.chars.peek() else { break };
+
+            match c {
+                // A tag after movetext means the previous game ended without a result.
+                '[' if game.is_some() => break,
+                '[' => tags.push(self.read_tag()?),
+                '{' => self.skip_until('}'),
+                ';' => self.skip_until('\n'),
+                '%' => self.skip_until('\n'),
+                '(' => self.skip_variation()?,
+                ')' => return Err(in_game(number, Error::InvalidPgn("unmatched ')' in movetext".to_string()))),
+                '$' => {
+                    self.chars.next();
+                    self.read_symbol();
+                }
+                _ => {
+                    let token = self.read_symbol();
+                    if token.is_empty() {
+                        return Err(in_game(number, Error::InvalidPgn(format!("unexpected character '{}'", c))));
+                    }
+                    if is_result(&token) {
+                        result = Some(token);
+                        break;
+                    }
+
+                    let san = strip_move_number(&token);
+                    if san.is_empty() || san.chars().all(|c| c == '!' || c == '?') {
+                        continue;
+                    }
+
+                    let current = match game.as_mut() {
+                        Some(current) => current,
+                        None => game.insert(starting_position(&tags, number)?),
+                    };
+                    current.make_san_move(san)
+                        .map_err(|e| Error::Within(format!("Game {}, move {}", number, current.history().len() + 1), Box::new(e)))?;
+                }
+            }
+        }
+
+        if tags.is_empty() && game.is_none() && result.is_none() {
+            return Ok(None);
+        }
+
+        let mut game = match game {
+            Some(game) => game,
+            None => starting_position(&tags, number)?,
+        };
+        if let Some(result) = result {
+            if !tags.iter().any(|(n, _)| n == "Result") {
+                tags.push(("Result".to_string(), result));
+            }
+        }
+        declare_outcome(&mut game, &tags);
+
+        Ok(Some(PgnGame { tags, game }))
+    }
+
+    fn read_tag(&mut self) -> Result<(String, String), Error> {
+        self.chars.next(); // '['
+        self.skip_whitespace();
+        let name = self.read_symbol();
+        self.skip_whitespace();
+        if self.chars.next() != Some('"') {
+            return Err(Error::InvalidPgn(format!("tag {} is missing its quoted value", name)));
+        }
+
+        let mut value = String::new();
+        loop {
+            match self.chars.next() {
+                Some('\\') => value.extend(self.chars.next()),
+                Some('"') => break,
+                Some(c) => value.push(c),
+                None => return Err(Error::InvalidPgn(format!("unterminated value for tag {}", name))),
+            }
+        }
+
+        self.skip_whitespace();
+        if self.chars.next() != Some(']') {
+            return Err(Error::InvalidPgn(format!("tag {} is missing its closing ']'", name)));
+        }
+        Ok((name, value))
+    }
+
+    /// Skips a parenthesised variation, including nested variations and comments.
+    fn skip_variation(&mut self) -> Result<(), Error> {
+        let mut depth = 0;
+        while let Some(c) = self.chars.next() {
+            match c {
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                '{' => self.skip_until('}'),
+                ';' => self.skip_until('\n'),
+                _ => {}
+            }
+        }
+        Err(Error::InvalidPgn("unterminated variation in movetext".to_string()))
+    }
+
+    /// Reads a run of characters up to the next delimiter.
+    fn read_symbol(&mut self) -> String {
+        let mut symbol = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c.is_whitespace() || "[]{}();$\"".contains(c) {
+                break;
+            }
+            symbol.push(c);
+            self.chars.next();
+        }
+        symbol
+    }
+
+    fn skip_until(&mut self, end: char) {
+        for c in self.chars.by_ref() {
+            if c == end {
+                break;
+            }
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+}
+
+/// Records a result the moves alone do not explain (a resignation, timeout, agreed or
+/// claimed draw, or adjudication) so that `Game::outcome` agrees with the PGN.
+fn declare_outcome(game: &mut Game, tags: &[(String, String)]) {
+    if game.outcome().is_some() {
+        return;
+    }
+    let tag = |name: &str| tags.iter().find(|(n, _)| n == name).map(|(_, v)| v.to_ascii_lowercase());
+    let termination = tag("Termination");
+
+    let result = match tag("Result").as_deref() {
+        Some("1-0") => GameResult::WhiteWin,
+        Some("0-1") => GameResult::BlackWin,
+        Some("1/2-1/2") => GameResult::Draw,
+        _ => return,
+    };
+    let loser = if result == GameResult::WhiteWin { Player::Black } else { Player::White };
+
+    match (termination.as_deref(), result) {
+        (Some("adjudication"), _) => game.adjudicate(result),
+        (Some("time forfeit"), GameResult::WhiteWin | GameResult::BlackWin) => game.lose_on_time(loser),
+        (_, GameResult::Draw) => {
+            if game.claim_draw().is_err() {
+                game.agree_draw();
+            }
+        }
+        _ => game.resign(loser),
+    }
+}
+
+/// `token` without a leading move number such as `12.` or `12...`. Digits without a dot are
+/// kept, so zero-castling (`0-0`) survives.
+fn strip_move_number(token: &str) -> &str {
+    let after_digits = token.trim_start_matches(|c: char| c.is_ascii_digit());
+    let after_dots = after_digits.trim_start_matches('.');
+    if after_digits.len() < token.len() && after_dots.len() < after_digits.len() {
+        after_dots
+    } else {
+        token
+    }
+}
+
+/// `e` as having happened in game `number` of the file.
+fn in_game(number: usize, e: Error) -> Error {
+    Error::Within(format!("Game {}", number), Box::new(e))
+}
+
+fn is_result(token: &str) -> bool {
+    matches!(token, "1-0" | "0-1" | "1/2-1/2" | "*")
+}
+
+/// The game's initial position: the `FEN` tag if there is one, otherwise the usual start of
+/// the game's `Variant`. Recorded games follow FIDE rules, where play may continue past an
+/// unclaimed repetition.
+fn starting_position(tags: &[(String, String)], number: usize) -> Result<Game, Error> {
+    let variant_tag = tags.iter().find(|(n, _)| n == "Variant").map(|(_, v)| v.as_str());
+    let chess960 = variant_tag.is_some_and(|v| matches!(v.to_lowercase().as_str(), "chess960" | "fischerandom"));
+    let variant = match variant_tag {
+        Some(name) if !chess960 => name.parse::<Variant>().map_err(|e| in_game(number, Error::InvalidPgn(e)))?,
+        _ => Variant::Standard,
+    };
+
+    let mut game = match tags.iter().find(|(n, _)| n == "FEN") {
+        Some((_, fen)) => Game::from_fen_variant(fen, variant).map_err(|e| in_game(number, e))?,
+        None => Game::with_variant(variant),
+    };
+    if chess960 {
+        game.set_chess960(true).map_err(|e| in_game(number, e))?;
+    }
+    game.set_draw_rules(DrawRules::Claimable);
+    Ok(game)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameOutcome, Termination};
+
+    const TWO_GAMES: &str = r#"
+[Event "Casual Game"]
+[Site "Berlin GER"]
+[Date "1852.??.??"]
+[Round "?"]
+[White "Adolf Anderssen"]
+[Black "Jean Dufresne"]
+[Result "1-0"]
+
+1.e4 e5 2.Nf3 Nc6 3.Bc4 Bc5 4.b4 Bxb4 5.c3 Ba5 6.d4 exd4 7.O-O d3 8.Qb3 Qf6
+9.e5 Qg6 10.Re1 Nge7 11.Ba3 b5 12.Qxb5 Rb8 13.Qa4 Bb6 14.Nbd2 Bb7 15.Ne4 Qf5
+16.Bxd3 Qh5 17.Nf6+ gxf6 18.exf6 Rg8 19.Rad1 Qxf3 20.Rxe7+ Nxe7 21.Qxd7+ Kxd7
+22.Bf5+ Ke8 23.Bd7+ Kf8 24.Bxe7# 1-0
+
+[Event "Annotated"]
+[Site "?"]
+[Date "????.??.??"]
+[Round "?"]
+[White "?"]
+[Black "?"]
+[Result "*"]
+
+{Opening comment} 1. e4 $1 {best by test} e5 (1... c5 2. Nf3 (2. c3) d6) 2. Nf3!? ; rest of line
+Nc6 3. Bb5 a6 *
+"#;
+
+    #[test]
+    fn test_parse_multiple_games() {
+        let games = parse_pgn(TWO_GAMES).expect("PGN should parse");
+        assert_eq!(games.len(), 2);
+
+        let evergreen = &games[0];
+        assert_eq!(evergreen.tag("White"), Some("Adolf Anderssen"));
+        assert_eq!(evergreen.result(), "1-0");
+        assert_eq!(evergreen.game.history().len(), 47);
+        assert!(evergreen.game.is_terminal(), "The Evergreen game ends in mate.");
+        assert_eq!(evergreen.game.get_game_result(), Some(GameResult::WhiteWin));
+
+        let annotated = &games[1];
+        assert_eq!(annotated.result(), "*");
+        let moves: Vec<String> = annotated.game.history().iter().map(|r| r.mv.to_string()).collect();
+        assert_eq!(moves, vec!["e2e4", "e7e5", "g1f3", "b8c6", "f1b5", "a7a6"],
+                   "Comments, NAGs and variations should not leak into the main line.");
+    }
+
+    #[test]
+    fn test_parse_fen_tag_and_black_to_move() {
+        let pgn = r#"[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1"]
+
+1... Kd7 2. e4 Kc6 1/2-1/2"#;
+        let games = parse_pgn(pgn).expect("PGN should parse");
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].result(), "1/2-1/2", "Result is taken from movetext when there is no tag.");
+        assert_eq!(games[0].game.history().len(), 3);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = parse_pgn("1. e4 e5 2. Ke3 *").unwrap_err();
+        assert!(matches!(&error, Error::Within(at, _) if at == "Game 1, move 3"), "Illegal moves should be rejected: {}", error);
+        assert!(parse_pgn("[Event \"Unterminated]").is_err());
+        assert!(parse_pgn("1. e4 (1. d4 *").is_err());
+        assert!(parse_pgn("  \n ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_zero_castling() {
+        let games = parse_pgn("1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4.0-0 Nf6 5. d3 0-0 *").expect("PGN should parse");
+        let moves: Vec<String> = games[0].game.history().iter().map(|r| r.mv.to_string()).collect();
+        assert_eq!(moves[6], "e1g1");
+        assert_eq!(moves[9], "e8g8");
+    }
+
+    #[test]
+    fn test_write_pgn_seven_tag_roster() {
+        let mut game = Game::new();
+        for san in ["f3", "e5", "g4", "Qh4#"] {
+            game.make_san_move(san).unwrap();
+        }
+
+        let pgn = write_pgn(&game, &[("White", "Engine A"), ("Black", "Engine B"), ("Annotator", "ChessAI")]);
+        let expected = "[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n\
+                        [White \"Engine A\"]\n[Black \"Engine B\"]\n[Result \"0-1\"]\n\
+                        [Termination \"normal\"]\n[Annotator \"ChessAI\"]\n\n\
+                        1. f3 e5 2. g4 Qh4# {Black wins by checkmate} 0-1\n";
+        assert_eq!(pgn, expected);
+    }
+
+    #[test]
+    fn test_write_then_parse_round_trip() {
+        let games = parse_pgn(TWO_GAMES).unwrap();
+        let original = &games[0];
+        let tags: Vec<(&str, &str)> = original.tags.iter().map(|(n, v)| (n.as_str(), v.as_str())).collect();
+
+        let written = write_pgn(&original.game, &tags);
+        assert!(written.lines().all(|line| line.len() < 80), "Export lines should stay under 80 characters.");
+
+        let reparsed = parse_pgn(&written).expect("Written PGN should parse");
+        assert_eq!(reparsed.len(), 1);
+        assert_eq!(reparsed[0].tags[..7], original.tags[..]);
+        assert_eq!(reparsed[0].tag("Termination"), Some("normal"));
+        assert_eq!(reparsed[0].game.history(), original.game.history());
+    }
+
+    #[test]
+    fn test_write_pgn_from_custom_position() {
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 3 31").unwrap();
+        game.make_san_move("Kd7").unwrap();
+        game.make_san_move("e4").unwrap();
+
+        let pgn = write_pgn(&game, &[("Event", "Say \"hi\"")]);
+        assert!(pgn.contains("[Event \"Say \\\"hi\\\"\"]"), "Quotes in tag values should be escaped.");
+        assert!(pgn.contains("[SetUp \"1\"]"));
+        assert!(pgn.contains("[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 3 31\"]"));
+        assert!(pgn.ends_with("31... Kd7 32. e4 *\n"), "Move numbers continue from the FEN.");
+
+        let reparsed = parse_pgn(&pgn).unwrap();
+        assert_eq!(reparsed[0].tag("Event"), Some("Say \"hi\""));
+        assert_eq!(reparsed[0].game.history(), game.history());
+    }
+
+    #[test]
+    fn test_chess960_round_trip() {
+        let mut game = Game::chess960(518).unwrap();
+        for uci in ["e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "f8c5", "e1h1"] {
+            game.make_move(uci).unwrap();
+        }
+
+        let pgn = write_pgn(&game, &[]);
+        assert!(pgn.contains("[Variant \"Chess960\"]"));
+        assert!(pgn.contains("[FEN \"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1\"]"));
+        assert!(pgn.contains("4. O-O *"));
+
+        let parsed = parse_pgn(&pgn).expect("Should parse");
+        assert!(parsed[0].game.is_chess960());
+        assert_eq!(parsed[0].game.to_fen(), game.to_fen());
+    }
+
+    #[test]
+    fn test_variant_round_trip() {
+        let mut game = Game::with_variant(Variant::ThreeCheck);
+        for san in ["e4", "e5", "Bc4", "Nc6", "Bxf7+", "Kxf7", "Qh5+", "g6", "Qxe5", "Nxe5"] {
+            game.make_san_move(san).unwrap();
+        }
+
+        let pgn = write_pgn(&game, &[]);
+        assert!(pgn.contains("[Variant \"Three-check\"]"));
+        assert!(!pgn.contains("[FEN"), "The usual start needs no FEN tag.");
+
+        let parsed = parse_pgn(&pgn).expect("Should parse");
+        assert_eq!(parsed[0].game.variant(), Variant::ThreeCheck);
+        assert_eq!(parsed[0].game.checks_given(Player::White), 2);
+        assert_eq!(parsed[0].game.to_fen(), game.to_fen());
+
+        let racing = write_pgn(&Game::with_variant(Variant::RacingKings), &[]);
+        assert!(racing.contains("[Variant \"Racing Kings\"]"));
+        assert_eq!(parse_pgn(&racing).unwrap()[0].game.to_fen(), "8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1");
+
+        let error = parse_pgn("[Variant \"Bughouse\"]\n\n1. e4 *").unwrap_err();
+        assert!(matches!(&error, Error::Within(_, e) if matches!(**e, Error::InvalidPgn(_))), "{}", error);
+    }
+
+    #[test]
+    fn test_termination_round_trip() {
+        let mut game = Game::new();
+        game.make_san_move("e4").unwrap();
+        game.lose_on_time(Player::Black);
+
+        let pgn = write_pgn(&game, &[]);
+        assert!(pgn.contains("[Termination \"time forfeit\"]"));
+        assert!(pgn.ends_with("1. e4 {White wins by timeout} 1-0\n"));
+        let reparsed = parse_pgn(&pgn).unwrap();
+        assert_eq!(reparsed[0].game.outcome(), game.outcome());
+
+        // Results the moves do not explain are read as resignations or agreed draws.
+        let games = parse_pgn("1. e4 e5 0-1\n\n1. d4 d5 1/2-1/2").unwrap();
+        assert_eq!(games[0].game.outcome(), Some(GameOutcome::new(GameResult::BlackWin, Termination::Resignation)));
+        assert_eq!(games[1].game.outcome(), Some(GameOutcome::new(GameResult::Draw, Termination::Agreement)));
+    }
+
+    #[test]
+    fn test_play_continues_past_unclaimed_repetition() {
+        let pgn = "1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1 Ng8 5. e4 e5 6. Nf3 Nc6 1-0";
+        let games = parse_pgn(pgn).expect("Unclaimed repetitions do not end recorded games");
+        assert_eq!(games[0].game.history().len(), 12);
+        assert_eq!(games[0].game.get_game_result(), Some(GameResult::WhiteWin));
+
+        let games = parse_pgn("1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1 Ng8 1/2-1/2").unwrap();
+        assert_eq!(games[0].game.outcome().unwrap().termination, Termination::ThreefoldRepetition);
+    }
+}